@@ -12,6 +12,13 @@ pub enum EC {
     PathValidation,
     ToDirCreation,
     SuppliedArgs,
+    ServerBind,
+    ConfigRead,
+    Shutdown,
+    Locked,
+    PathMissing,
+    Restore,
+    Undo,
 }
 
 pub fn get_exit_code(ec: &EC) -> i32 {
@@ -29,6 +36,13 @@ pub fn get_exit_code(ec: &EC) -> i32 {
         EC::PathValidation => 10,
         EC::ToDirCreation => 11,
         EC::SuppliedArgs => 12,
+        EC::ServerBind => 13,
+        EC::ConfigRead => 14,
+        EC::Shutdown => 15,
+        EC::Locked => 16,
+        EC::PathMissing => 17,
+        EC::Restore => 18,
+        EC::Undo => 19,
     }
 }
 
@@ -47,5 +61,12 @@ pub fn get_exit_msg(ec: &EC) -> String {
         EC::PathValidation => String::from("Error: The 'From' and 'To' paths cannot be the same."),
         EC::ToDirCreation => String::from("Error: Failed to create a new directory."),
         EC::SuppliedArgs => String::from("Error: Invalid arguments given."),
+        EC::ServerBind => String::from("Error: Unable to listen on the given address."),
+        EC::ConfigRead => String::from("Error: Unable to read the configuration file."),
+        EC::Shutdown => String::from("Stopped by a shutdown signal."),
+        EC::Locked => String::from("Error: Another mover is already running for this 'From' path."),
+        EC::PathMissing => String::from("Error: The 'From' or 'To' path was missing for too long."),
+        EC::Restore => String::from("Error: Unable to restore the file."),
+        EC::Undo => String::from("Error: Some transfers could not be undone."),
     }
 }
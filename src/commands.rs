@@ -0,0 +1,400 @@
+use super::codes::*;
+use super::options::DEFAULT_LOG_DIR;
+use super::records;
+use super::stats::format_bytes;
+use super::undo::Undo;
+use super::versions::Versions;
+use chrono::{DateTime, Local, NaiveDate};
+use regex::Regex;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/**
+ * Runs a subcommand, e.g. "mover restore <path>", and exits. Returns when the arguments are not a
+ * subcommand, so they can be read as a 'from' and 'to' directory. A directory named like a
+ * subcommand wins, so "mover history to" still moves the files of "history".
+ */
+pub fn run(args: &[String]) {
+    let word = args.get(1).filter(|arg| !Path::new(arg).is_dir());
+    match word.map(|arg| arg.as_str()) {
+        Some("restore") => restore(&args[2..]),
+        Some("undo") => undo(&args[2..]),
+        Some("history") => history(&args[2..]),
+        _ => return,
+    }
+    process::exit(get_exit_code(&EC::Success));
+}
+
+/**
+ * Prints the error of a subcommand and exits. Subcommands don't write to the log.
+ */
+fn fail<T: Into<String>>(ec: &EC, err: T) {
+    eprintln!("{}\n{}", get_exit_msg(ec), err.into());
+    process::exit(get_exit_code(ec));
+}
+
+/**
+ * The value following the option at 'index'.
+ */
+fn value(args: &[String], index: usize) -> String {
+    match args.get(index + 1) {
+        Some(value) => value.to_string(),
+        None => {
+            fail(
+                &EC::SuppliedArgs,
+                format!("The option {} needs a value.", args[index]),
+            );
+            String::new()
+        }
+    }
+}
+
+/**
+ * The time following the option at 'index', and whether it was only a date.
+ */
+fn time_value(args: &[String], index: usize) -> (DateTime<Local>, bool) {
+    let value = value(args, index);
+    match records::parse_time(&value) {
+        Some(time) => (time, NaiveDate::parse_from_str(&value, "%F").is_ok()),
+        None => {
+            fail(
+                &EC::SuppliedArgs,
+                format!(
+                    "Invalid time \"{}\" for {}. Expected YYYY-MM-DD, optionally followed by HH:MM or HH:MM:SS.",
+                    value, args[index]
+                ),
+            );
+            (Local::now(), false)
+        }
+    }
+}
+
+/**
+ * mover restore <path> [/version <n>] [/versionsdir <directory> /to <to-directory>]
+ *
+ * Lists the versions kept of a file in the 'to' directory, or puts back version n, 1 being the
+ * newest. With /versionsdir, the 'to' directory is needed to find the file under it.
+ */
+fn restore(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => PathBuf::from(path),
+        None => {
+            fail(
+                &EC::SuppliedArgs,
+                "Usage: mover restore <path> [/version <n>]",
+            );
+            return;
+        }
+    };
+    let mut version = None;
+    let mut root = None;
+    let mut to_dir = None;
+    for index in (1..args.len()).step_by(2) {
+        match args[index].as_str() {
+            "/version" | "--version" => match value(args, index).parse::<usize>() {
+                Ok(n) if n > 0 => version = Some(n),
+                _ => fail(&EC::SuppliedArgs, "Invalid value for /version."),
+            },
+            "/versionsdir" => root = Some(PathBuf::from(value(args, index))),
+            "/to" => to_dir = Some(PathBuf::from(value(args, index))),
+            arg => fail(&EC::SuppliedArgs, format!("Unknown option \"{}\".", arg)),
+        }
+    }
+    if root.is_some() && to_dir.is_none() {
+        fail(
+            &EC::SuppliedArgs,
+            "/versionsdir needs the 'to' directory given with /to.",
+        );
+    }
+    let to_dir = to_dir.unwrap_or_default();
+    let versions = Versions {
+        to_dir: &to_dir,
+        root: root.as_deref(),
+    };
+    match version {
+        Some(n) => match versions.restore(&path, n) {
+            Ok(restored) => println!(
+                "Restored \"{}\" from \"{}\".",
+                path.to_str().unwrap(),
+                restored.to_str().unwrap()
+            ),
+            Err(e) => fail(&EC::Restore, e),
+        },
+        None => match versions.list(&path) {
+            Ok(list) if list.is_empty() => println!(
+                "No versions of \"{}\" in \"{}\".",
+                path.to_str().unwrap(),
+                versions.dir_for(&path).to_str().unwrap()
+            ),
+            Ok(list) => {
+                for (i, version) in list.iter().enumerate() {
+                    println!(
+                        "{:>4}  {}  {}",
+                        i + 1,
+                        version.replaced.format("%Y-%m-%d %H:%M:%S"),
+                        version.path.to_str().unwrap()
+                    );
+                }
+            }
+            Err(e) => fail(&EC::Restore, e.to_string()),
+        },
+    }
+}
+
+/**
+ * mover undo (/since <time> | /session <id>) [/label <label>] [/log <log-directory>] [/dryrun]
+ *
+ * Reverses the recorded transfers since a time or of one session, newest first: moved files go
+ * back where they came from, copies are removed, overwritten files get their old version back and
+ * removed directories are recreated. Conflicts are reported and left alone.
+ */
+fn undo(args: &[String]) {
+    let mut since = None;
+    let mut session = None;
+    let mut label = None;
+    let mut log_dir = PathBuf::from(DEFAULT_LOG_DIR);
+    let mut dry_run = false;
+    let mut index = 0;
+    while index < args.len() {
+        match args[index].as_str() {
+            "/since" | "--since" => since = Some(time_value(args, index).0),
+            "/session" | "--session" => session = Some(value(args, index)),
+            "/label" => label = Some(value(args, index)),
+            "/log" => log_dir = PathBuf::from(value(args, index)),
+            "/dryrun" | "--dry-run" => {
+                dry_run = true;
+                index += 1;
+                continue;
+            }
+            arg => fail(&EC::SuppliedArgs, format!("Unknown option \"{}\".", arg)),
+        }
+        index += 2;
+    }
+    if since.is_none() && session.is_none() {
+        fail(
+            &EC::SuppliedArgs,
+            "Usage: mover undo (/since <time> | /session <id>) [/label <label>] [/log <log-directory>] [/dryrun]",
+        );
+    }
+    let found = records::find(
+        &log_dir,
+        since.map(|since| since.date_naive()),
+        None,
+        |entry| {
+            session
+                .as_ref()
+                .is_none_or(|session| entry.session == *session)
+                && label.as_ref().is_none_or(|label| entry.label == *label)
+        },
+    );
+    let all = match found {
+        Ok(all) => all,
+        Err(e) => {
+            let dir = records::dir(&log_dir);
+            let msg = format!(
+                "Unable to read the transfer records in \"{}\": {}",
+                dir.to_str().unwrap(),
+                e
+            );
+            fail(&EC::Undo, msg);
+            return;
+        }
+    };
+    let matching = all.iter().filter(|record| {
+        since.is_none_or(|since| records::time(record).is_some_and(|time| time >= since))
+    });
+    let mut undo = Undo::new(dry_run);
+    let mut done = 0;
+    let mut conflicts = 0;
+    for record in matching.rev() {
+        match undo.apply(record) {
+            Some(Ok(msg)) => {
+                println!("{}", msg);
+                done += 1;
+            }
+            Some(Err(msg)) => {
+                println!("Conflict: {}", msg);
+                conflicts += 1;
+            }
+            None => {}
+        }
+    }
+    if dry_run {
+        println!(
+            "Dry run: {} change(s) and {} conflict(s). Nothing was changed.",
+            done, conflicts
+        );
+    } else {
+        println!("Undid {} change(s) with {} conflict(s).", done, conflicts);
+    }
+    if conflicts > 0 {
+        process::exit(get_exit_code(&EC::Undo));
+    }
+}
+
+/**
+ * mover history [/file <glob>] [/since <time>] [/until <time>] [/label <label>]
+ *               [/status <moved|copied|failed>] [/dest <glob>] [/format <table|csv|json>]
+ *               [/log <log-directory>]
+ *
+ * Lists past transfers from the transfer records, oldest first. The records are kept by day, so
+ * only the days between /since and /until are read, and only the records whose index entry matches
+ * the other filters.
+ */
+fn history(args: &[String]) {
+    let mut file = None;
+    let mut since = None;
+    let mut until = None;
+    let mut label = None;
+    let mut status = None;
+    let mut dest = None;
+    let mut format = String::from("table");
+    let mut log_dir = PathBuf::from(DEFAULT_LOG_DIR);
+    for index in (0..args.len()).step_by(2) {
+        match args[index].as_str() {
+            "/file" => file = Some(glob(&value(args, index))),
+            "/since" => since = Some(time_value(args, index).0),
+            "/until" => {
+                // A date alone includes the whole day.
+                let (time, date_only) = time_value(args, index);
+                until = Some(if date_only {
+                    time + chrono::Duration::days(1)
+                } else {
+                    time
+                });
+            }
+            "/label" => label = Some(value(args, index)),
+            "/status" => match value(args, index).as_str() {
+                status_value @ ("moved" | "copied" | "failed") => {
+                    status = Some(status_value.to_string())
+                }
+                _ => fail(&EC::SuppliedArgs, "Invalid value for /status."),
+            },
+            "/dest" => dest = Some(glob(&value(args, index))),
+            "/format" => match value(args, index).as_str() {
+                format_value @ ("table" | "csv" | "json") => format = format_value.to_string(),
+                _ => fail(&EC::SuppliedArgs, "Invalid value for /format."),
+            },
+            "/log" => log_dir = PathBuf::from(value(args, index)),
+            arg => fail(&EC::SuppliedArgs, format!("Unknown option \"{}\".", arg)),
+        }
+    }
+    let found = records::find(
+        &log_dir,
+        since.map(|since| since.date_naive()),
+        until.map(|until| until.date_naive()),
+        |entry| {
+            matches!(entry.action.as_str(), "moved" | "copied" | "failed")
+                && status.as_ref().is_none_or(|status| entry.action == *status)
+                && label.as_ref().is_none_or(|label| entry.label == *label)
+                && file.as_ref().is_none_or(|file| {
+                    file.is_match(&entry.from_name) || file.is_match(&entry.to_name)
+                })
+                && dest.as_ref().is_none_or(|dest| dest.is_match(&entry.to))
+        },
+    );
+    let all = match found {
+        Ok(all) => all,
+        Err(e) => {
+            let dir = records::dir(&log_dir);
+            let msg = format!(
+                "Unable to read the transfer records in \"{}\": {}",
+                dir.to_str().unwrap(),
+                e
+            );
+            fail(&EC::IOGeneric, msg);
+            return;
+        }
+    };
+    let transfers: Vec<&Value> = all
+        .iter()
+        .filter(|record| {
+            let time = records::time(record);
+            since.is_none_or(|since| time.is_some_and(|time| time >= since))
+                && until.is_none_or(|until| time.is_some_and(|time| time < until))
+        })
+        .collect();
+    match format.as_str() {
+        "json" => println!(
+            "{}",
+            serde_json::to_string_pretty(&transfers).unwrap_or_default()
+        ),
+        "csv" => {
+            println!("time,status,label,bytes,from,to,session,error");
+            for record in transfers {
+                let fields = [
+                    records::text(record, "time").to_string(),
+                    records::text(record, "action").to_string(),
+                    records::text(record, "label").to_string(),
+                    record["bytes"]
+                        .as_u64()
+                        .map_or(String::new(), |b| b.to_string()),
+                    records::text(record, "from").to_string(),
+                    records::text(record, "to").to_string(),
+                    records::text(record, "session").to_string(),
+                    records::text(record, "error").to_string(),
+                ];
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|field| records::csv_field(field))
+                    .collect();
+                println!("{}", fields.join(","));
+            }
+        }
+        _ => {
+            let mut rows = vec![[
+                String::from("Time"),
+                String::from("Status"),
+                String::from("Label"),
+                String::from("Size"),
+                String::from("From"),
+                String::from("To"),
+            ]];
+            for record in &transfers {
+                rows.push([
+                    records::time(record)
+                        .map_or(String::new(), |time| time.format("%F %T").to_string()),
+                    records::text(record, "action").to_string(),
+                    records::text(record, "label").to_string(),
+                    record["bytes"]
+                        .as_u64()
+                        .map_or(String::from("-"), |bytes| format_bytes(bytes as f64)),
+                    records::text(record, "from").to_string(),
+                    records::text(record, "to").to_string(),
+                ]);
+            }
+            let mut widths = [0; 6];
+            for row in &rows {
+                for (width, cell) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(cell.chars().count());
+                }
+            }
+            for row in &rows {
+                let cells: Vec<String> = row
+                    .iter()
+                    .zip(widths)
+                    .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                    .collect();
+                println!("{}", cells.join("  ").trim_end());
+            }
+            println!("{} transfer(s).", transfers.len());
+        }
+    }
+}
+
+/**
+ * A glob where '*' matches anything, including path separators, and '?' matches one character.
+ */
+fn glob(pattern: &str) -> Regex {
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).unwrap()
+}
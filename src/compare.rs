@@ -0,0 +1,151 @@
+use file_diff::diff;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/**
+ * Modification times closer together than this are treated as equal. Some file systems (FAT,
+ * many network shares) only store times to the nearest two seconds.
+ */
+const MTIME_TOLERANCE: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mode {
+    // Same size and modification time.
+    SizeMtime,
+    // Same SHA-256 checksum. Checksums are cached until a file's size or mtime changes.
+    Checksum,
+    // Same content, byte for byte.
+    Full,
+}
+
+impl Mode {
+    pub fn parse(value: &str) -> Option<Mode> {
+        match value {
+            "size+mtime" => Some(Mode::SizeMtime),
+            "checksum" => Some(Mode::Checksum),
+            "full" => Some(Mode::Full),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Mode::SizeMtime => "size+mtime",
+            Mode::Checksum => "checksum",
+            Mode::Full => "full",
+        }
+    }
+}
+
+struct CachedHash {
+    len: u64,
+    mtime: SystemTime,
+    hash: String,
+}
+
+/**
+ * Decides whether a file and its existing destination are identical.
+ */
+pub struct Comparer {
+    mode: Mode,
+    hashes: HashMap<PathBuf, CachedHash>,
+}
+
+impl Default for Comparer {
+    fn default() -> Comparer {
+        Comparer {
+            mode: Mode::SizeMtime,
+            hashes: HashMap::new(),
+        }
+    }
+}
+
+impl Comparer {
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    /**
+     * Returns true if both files are identical. Any error reading either file counts as a
+     * difference, so the file will be copied and the error logged there.
+     */
+    pub fn identical(&mut self, from_path: &Path, to_path: &Path) -> bool {
+        let (from_meta, to_meta) = match (fs::metadata(from_path), fs::metadata(to_path)) {
+            (Ok(from_meta), Ok(to_meta)) => (from_meta, to_meta),
+            _ => return false,
+        };
+        if from_meta.len() != to_meta.len() {
+            return false;
+        }
+        match self.mode {
+            Mode::SizeMtime => match (from_meta.modified(), to_meta.modified()) {
+                (Ok(from_mtime), Ok(to_mtime)) => {
+                    let delta = match from_mtime.duration_since(to_mtime) {
+                        Ok(delta) => delta,
+                        Err(e) => e.duration(),
+                    };
+                    delta < MTIME_TOLERANCE
+                }
+                _ => false,
+            },
+            Mode::Checksum => match (self.checksum(from_path), self.checksum(to_path)) {
+                (Ok(from_hash), Ok(to_hash)) => from_hash == to_hash,
+                _ => false,
+            },
+            Mode::Full => diff(from_path.to_str().unwrap(), to_path.to_str().unwrap()),
+        }
+    }
+
+    /**
+     * Returns the SHA-256 checksum of a file as a hex string, using the cached value if the file's
+     * size and modification time have not changed.
+     */
+    pub fn checksum(&mut self, path: &Path) -> io::Result<String> {
+        let meta = fs::metadata(path)?;
+        let mtime = meta.modified()?;
+        if let Some(cached) = self.hashes.get(path) {
+            if cached.len == meta.len() && cached.mtime == mtime {
+                return Ok(cached.hash.clone());
+            }
+        }
+        let hash = sha256(path)?;
+        self.hashes.insert(
+            path.to_path_buf(),
+            CachedHash {
+                len: meta.len(),
+                mtime,
+                hash: hash.clone(),
+            },
+        );
+        Ok(hash)
+    }
+
+    /**
+     * Drops cached checksums of files that no longer exist.
+     */
+    pub fn prune(&mut self) {
+        self.hashes.retain(|path, _| path.exists());
+    }
+}
+
+/**
+ * Reads a file and returns its SHA-256 checksum as a hex string.
+ */
+pub fn sha256(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
@@ -0,0 +1,15 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/**
+ * Moves a file. Renaming fails across file systems, so this falls back to copying the file and
+ * removing the original.
+ */
+pub fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
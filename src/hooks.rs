@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/**
+ * External commands run around transfers. Commands are run by the system shell, with details of
+ * the file or cycle passed in MOVER_* environment variables.
+ */
+pub struct Hooks {
+    pub before: Option<String>,
+    pub on_file: Option<String>,
+    pub on_cycle: Option<String>,
+    pub timeout: Duration,
+    // Files a before_transfer hook turned down, so the veto is only logged once per file, with
+    // the size, modified time and hash they had, so an unchanged file is not hashed again.
+    vetoed: HashMap<PathBuf, Option<(u64, SystemTime, String)>>,
+}
+
+impl Default for Hooks {
+    fn default() -> Hooks {
+        Hooks {
+            before: None,
+            on_file: None,
+            on_cycle: None,
+            timeout: Duration::from_secs(60),
+            vetoed: HashMap::new(),
+        }
+    }
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.before.is_none() && self.on_file.is_none() && self.on_cycle.is_none()
+    }
+
+    pub fn describe(&self) -> String {
+        let mut hooks = Vec::new();
+        if let Some(command) = &self.before {
+            hooks.push(format!("before_transfer \"{}\"", command));
+        }
+        if let Some(command) = &self.on_file {
+            hooks.push(format!("on_file_transferred \"{}\"", command));
+        }
+        if let Some(command) = &self.on_cycle {
+            hooks.push(format!("on_cycle_complete \"{}\"", command));
+        }
+        format!("{} (timeout {}s)", hooks.join(", "), self.timeout.as_secs())
+    }
+
+    /**
+     * Remembers a vetoed file, with its size, modified time and hash if they are known. Returns
+     * true the first time a file is vetoed.
+     */
+    pub fn veto(&mut self, path: &Path, hashed: Option<(u64, SystemTime, String)>) -> bool {
+        self.vetoed.insert(path.to_path_buf(), hashed).is_none()
+    }
+
+    pub fn is_vetoed(&self, path: &Path) -> bool {
+        self.vetoed.contains_key(path)
+    }
+
+    pub fn clear_veto(&mut self, path: &Path) {
+        self.vetoed.remove(path);
+    }
+
+    /**
+     * Takes over the vetoed files of 'other', e.g. when the configuration is reloaded.
+     */
+    pub fn keep_vetoes(&mut self, other: &mut Hooks) {
+        self.vetoed = std::mem::take(&mut other.vetoed);
+    }
+
+    /**
+     * The hash of a vetoed file, if it still has the size and modified time it had then.
+     */
+    pub fn cached_hash(&self, path: &Path, size: u64, mtime: SystemTime) -> Option<String> {
+        match self.vetoed.get(path) {
+            Some(Some((cached_size, cached_mtime, hash)))
+                if *cached_size == size && *cached_mtime == mtime =>
+            {
+                Some(hash.clone())
+            }
+            _ => None,
+        }
+    }
+}
+
+/**
+ * How a hook command ended.
+ */
+pub struct Outcome {
+    // None if the command was stopped for running too long.
+    pub status: Option<ExitStatus>,
+    // Standard output followed by standard error.
+    pub output: String,
+}
+
+impl Outcome {
+    pub fn success(&self) -> bool {
+        self.status.is_some_and(|s| s.success())
+    }
+}
+
+/**
+ * Runs a command through the system shell with the given environment variables, stopping it if it
+ * runs longer than 'timeout'.
+ */
+pub fn run(command: &str, env: &[(&str, String)], timeout: Duration) -> io::Result<Outcome> {
+    let mut child = shell(command)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+    let status = wait_with_timeout(&mut child, timeout)?;
+    // A command that was stopped may have left children holding the pipes open, so only wait a
+    // little for the rest of the output and keep what was read so far.
+    let grace = match status {
+        Some(_) => Duration::from_secs(1),
+        None => Duration::from_millis(100),
+    };
+    let mut output = stdout.finish(grace);
+    output.push_str(&stderr.finish(grace));
+    Ok(Outcome { status, output })
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+/**
+ * Output read from a pipe by a background thread.
+ */
+struct Pipe {
+    output: Arc<Mutex<Vec<u8>>>,
+    done: Receiver<()>,
+}
+
+impl Pipe {
+    fn finish(self, grace: Duration) -> String {
+        let _ = self.done.recv_timeout(grace);
+        let output = self.output.lock().unwrap();
+        String::from_utf8_lossy(&output).to_string()
+    }
+}
+
+fn read_in_background<R: Read + Send + 'static>(pipe: Option<R>) -> Pipe {
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let (sender, done) = mpsc::channel();
+    if let Some(mut pipe) = pipe {
+        let output = Arc::clone(&output);
+        thread::spawn(move || {
+            let mut buf = [0; 4096];
+            while let Ok(n) = pipe.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                output.lock().unwrap().extend_from_slice(&buf[..n]);
+            }
+            let _ = sender.send(());
+        });
+    }
+    Pipe { output, done }
+}
+
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,59 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::time::Duration;
+
+/**
+ * How long a client may take to send its request or read the response. Requests are answered one
+ * at a time, so a client that stalls must not hold up the others for long.
+ */
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/**
+ * The parts of an HTTP request the local endpoints care about.
+ */
+pub struct Request {
+    pub method: String,
+    pub path: String,
+}
+
+/**
+ * Reads the request line and headers of an HTTP request. Request bodies are not used and ignored.
+ */
+pub fn read_request<S: Read>(stream: S) -> io::Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Read the rest of the headers so closing the connection does not reset it.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim_end().is_empty() {
+        header.clear();
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let path = parts.next().unwrap_or("").to_string();
+    // Query strings are not used by any endpoint.
+    let path = match path.find('?') {
+        Some(index) => path[..index].to_string(),
+        None => path,
+    };
+    Ok(Request { method, path })
+}
+
+/**
+ * Writes a complete response and closes the exchange.
+ */
+pub fn write_response<S: Write>(
+    mut stream: S,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
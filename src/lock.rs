@@ -0,0 +1,82 @@
+use fs2::FileExt;
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/**
+ * An advisory lock held for as long as the program runs, so only one mover works on a 'from'
+ * directory at a time. The lock file holds the PID of its owner.
+ */
+pub struct Lock {
+    path: PathBuf,
+    // Closing the file releases the lock.
+    _file: File,
+}
+
+impl Lock {
+    /**
+     * The lock file for a job: ".mover.lock" inside the 'from' directory, or a file in 'lock_dir'
+     * named after the label and the 'from' directory, so jobs sharing a lock directory never
+     * share a lock file.
+     */
+    pub fn path_for(from_dir: &Path, lock_dir: Option<&Path>, label: &str) -> PathBuf {
+        match lock_dir {
+            None => from_dir.join(".mover.lock"),
+            Some(lock_dir) => {
+                let from_dir = from_dir.canonicalize().unwrap_or(from_dir.to_path_buf());
+                let hash = format!(
+                    "{:x}",
+                    Sha256::digest(from_dir.to_string_lossy().as_bytes())
+                );
+                let label: String = label
+                    .chars()
+                    .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                    .collect();
+                lock_dir.join(format!("{}-{}.lock", label, &hash[..12]))
+            }
+        }
+    }
+
+    /**
+     * Takes the lock at 'path' without waiting. Returns None if another process holds it.
+     */
+    pub fn try_acquire(path: &Path) -> io::Result<Option<Lock>> {
+        // Not truncated on open, the PID of the current owner must stay readable.
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        match FileExt::try_lock_exclusive(&file) {
+            Ok(()) => {}
+            Err(e) if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() => {
+                return Ok(None)
+            }
+            Err(e) => return Err(e),
+        }
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        writeln!(file, "{}", process::id())?;
+        file.flush()?;
+        Ok(Some(Lock {
+            path: path.to_path_buf(),
+            _file: file,
+        }))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/**
+ * The PID written to a lock file by its owner, if it can be read.
+ */
+pub fn holder(path: &Path) -> Option<u32> {
+    let mut contents = String::new();
+    File::open(path).ok()?.read_to_string(&mut contents).ok()?;
+    contents.trim().parse().ok()
+}
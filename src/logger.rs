@@ -1,15 +1,83 @@
 use super::{get_exit_code, get_exit_msg, EC};
 // use super::exit_codes::*; Why does this not work here but in options.rs?
-use chrono::offset;
+use chrono::{offset, DateTime, Local};
 use std::fs;
 use std::fs::OpenOptions;
 use std::io::{BufWriter, ErrorKind, Write};
 use std::path::PathBuf;
 
+#[path = "retention.rs"]
+mod retention;
+pub use retention::Retention;
+
+#[path = "sinks.rs"]
+mod sinks;
+#[cfg(unix)]
+pub use sinks::{Journald, JOURNALD_SOCKET};
+pub use sinks::{Sink, Syslog};
+
+/**
+ * How important a log entry is. Each output has a threshold and only writes entries at or above it.
+ */
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn parse(value: &str) -> Option<Level> {
+        match value.to_lowercase().as_str() {
+            "error" => Some(Level::Error),
+            "warn" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+/**
+ * A buffered log entry. 'text' is what the console and the log file show, while other sinks get the
+ * plain 'msg' and its structured 'fields'.
+ */
+struct Entry {
+    level: Level,
+    text: String,
+    msg: String,
+    fields: Vec<(&'static str, String)>,
+}
+
+/**
+ * Where logs are written unless /log is given.
+ */
+pub const DEFAULT_DIR: &str = "C:\\mover\\logs";
+
 pub struct Logger {
     directory: PathBuf,
-    entries: Vec<String>,
+    entries: Vec<Entry>,
+    sinks: Vec<Box<dyn Sink>>,
+    failed_sinks: Vec<String>,
+    console_level: Level,
+    file_level: Level,
     flagged_paths: Vec<PathBuf>,
+    retention: Retention,
+    current_date: String,
+    last_error: Option<(DateTime<Local>, String)>,
 }
 
 impl Logger {
@@ -23,9 +91,16 @@ impl Logger {
 impl Default for Logger {
     fn default() -> Logger {
         Logger {
-            directory: PathBuf::from("C:\\mover\\logs"),
-            entries: Vec::<String>::new(),
+            directory: PathBuf::from(DEFAULT_DIR),
+            entries: Vec::<Entry>::new(),
+            sinks: Vec::new(),
+            failed_sinks: Vec::new(),
+            console_level: Level::Info,
+            file_level: Level::Info,
             flagged_paths: Vec::<PathBuf>::new(),
+            retention: Retention::default(),
+            current_date: String::new(),
+            last_error: None,
         }
     }
 }
@@ -37,35 +112,115 @@ impl Logger {
      */
     pub fn log_error<S: Into<String>>(&mut self, path: PathBuf, msg: S) {
         if !self.flagged_paths.contains(&path) {
-            let mut entry = self.get_date_time();
-            entry.push_str("\t");
-            entry.push_str(msg.into().as_str());
-            entry.push_str("\n\n");
-            self.entries.push(entry);
+            self.log(Level::Error, msg);
             self.flagged_paths.push(path);
         }
     }
 
+    /**
+     * Adds a log entry at the given level to the log buffer. Entries at every level other than
+     * info are tagged with their level.
+     */
+    pub fn log<S: Into<String>>(&mut self, level: Level, msg: S) {
+        self.log_fields(level, msg, Vec::new());
+    }
+
+    /**
+     * Same as log(), with structured fields for sinks that support them, e.g. ("EC", "12").
+     */
+    pub fn log_fields<S: Into<String>>(
+        &mut self,
+        level: Level,
+        msg: S,
+        fields: Vec<(&'static str, String)>,
+    ) {
+        let msg = msg.into();
+        if level == Level::Error {
+            self.last_error = Some((offset::Local::now(), msg.clone()));
+        }
+        let mut text = self.get_date_time();
+        text.push('\t');
+        if level != Level::Info {
+            text.push_str(level.as_str());
+            text.push('\t');
+        }
+        text.push_str(msg.as_str());
+        text.push_str("\n\n");
+        self.entries.push(Entry {
+            level,
+            text,
+            msg,
+            fields,
+        });
+    }
+
+    pub fn log_debug<S: Into<String>>(&mut self, msg: S) {
+        self.log(Level::Debug, msg);
+    }
+
+    pub fn log_trace<S: Into<String>>(&mut self, msg: S) {
+        self.log(Level::Trace, msg);
+    }
+
+    pub fn set_console_level(&mut self, level: Level) {
+        self.console_level = level;
+    }
+
+    pub fn set_file_level(&mut self, level: Level) {
+        self.file_level = level;
+    }
+
+    pub fn console_level(&self) -> Level {
+        self.console_level
+    }
+
+    pub fn file_level(&self) -> Level {
+        self.file_level
+    }
+
+    /**
+     * Returns the time and message of the most recent error.
+     */
+    pub fn last_error(&self) -> Option<&(DateTime<Local>, String)> {
+        self.last_error.as_ref()
+    }
+
+    pub fn add_sink(&mut self, sink: Box<dyn Sink>) {
+        self.sinks.push(sink);
+    }
+
+    /**
+     * Returns the names and levels of the extra sinks for the start up info.
+     */
+    pub fn describe_sinks(&self) -> String {
+        let sinks: Vec<String> = self
+            .sinks
+            .iter()
+            .map(|sink| format!("{} ({})", sink.name(), sink.level().as_str()))
+            .collect();
+        sinks.join(", ")
+    }
+
     /**
      * Returns the program's log directory path.
      */
     pub fn dir_as_str(&self) -> &str {
-        // TODO: This will not produce a crash while hard-coded. Once the program starts accepting a
-        // new log location through the /log option, it will need to be validated before being used.
+        // The directory is not validated here. Any problem with it is reported when the first log
+        // file is opened.
         self.directory.to_str().unwrap()
     }
 
+    pub fn set_directory(&mut self, directory: PathBuf) {
+        self.directory = directory;
+    }
+
     /**
      * Optionally takes a final log entry and flushes any buffered logs, writing them to the disk.
      */
     pub fn log_and_flush<S: Into<String>>(&mut self, msg: S) {
         let msg = msg.into();
         if !&msg.is_empty() {
-            let mut entry = self.get_date_time();
-            entry.push_str("\t");
-            entry.push_str(msg.as_str());
-            entry.push_str("\n\n");
-            self.entries.push(entry);
+            self.log(Level::Info, msg);
         }
         self.flush_logs();
     }
@@ -74,7 +229,14 @@ impl Logger {
      * Adds a log entry to the log buffer. This log function takes a 'from' and 'to' directory and
      * formats it for entry.
      */
-    pub fn log_transfer<F: Into<String>, T: Into<String>>(&mut self, from: F, to: T, copy: bool) {
+    pub fn log_transfer<F: Into<String>, T: Into<String>>(
+        &mut self,
+        from: F,
+        to: T,
+        copy: bool,
+        bytes: u64,
+        renamed: Option<String>,
+    ) {
         let from_string = from.into();
         let mut entry = self.get_date_time();
         if copy {
@@ -88,31 +250,73 @@ impl Logger {
         } else {
             entry.push_str("\"\nMoved To:\t\"");
         }
-        entry.push_str(to.into().as_str());
-        entry.push_str("\"\n\n");
-        self.entries.push(entry);
+        let to_string = to.into();
+        entry.push_str(to_string.as_str());
+        entry.push('"');
+        // Record both names when a rename rule changed the file name.
+        if let Some(renamed) = renamed {
+            let from_name = PathBuf::from(&from_string)
+                .file_name()
+                .map_or(String::new(), |n| n.to_string_lossy().to_string());
+            entry.push_str(format!("\nRenamed:\t\"{}\" -> \"{}\"", from_name, renamed).as_str());
+        }
+        entry.push_str("\n\n");
+        self.entries.push(Entry {
+            level: Level::Info,
+            text: entry,
+            msg: format!(
+                "{} \"{}\" to \"{}\"",
+                if copy { "Copied" } else { "Moved" },
+                from_string,
+                to_string
+            ),
+            fields: vec![
+                ("SOURCE", from_string.clone()),
+                ("DEST", to_string),
+                ("BYTES", bytes.to_string()),
+            ],
+        });
 
         // Clear any flagged paths after a successful transfer.
         self.remove_flagged_path(PathBuf::from(from_string));
     }
 
     pub fn log_info<T: Into<String>>(&mut self, info: T) {
-        let mut entry = self.get_date_time();
-        entry.push_str("\t");
-        entry.push_str(info.into().as_str());
-        entry.push_str("\n\n");
-        self.entries.push(entry);
+        self.log(Level::Info, info);
+    }
+
+    pub fn retention(&mut self) -> &mut Retention {
+        &mut self.retention
     }
 
     /**
-     * Writes all logs to disk.
+     * Writes all logs to disk and the console, each filtered by its own level. Old log files are
+     * cleaned up on the first write and whenever the date changes.
      */
     pub fn flush_logs(&mut self) {
         if !self.entries.is_empty() {
-            let mut log_file = self.get_buf_writer().unwrap();
+            let date = self.get_date();
+            if date != self.current_date {
+                self.current_date = date;
+                self.clean_logs();
+            }
+            self.send_to_sinks();
+            let file_level = self.file_level;
+            let mut log_file = if self.entries.iter().any(|entry| entry.level <= file_level) {
+                self.get_buf_writer()
+            } else {
+                None
+            };
             for entry in &self.entries {
-                match write!(log_file, "{}", entry) {
-                    Ok(()) => print!("{}", entry),
+                if entry.level <= self.console_level {
+                    print!("{}", entry.text);
+                }
+                let log_file = match log_file.as_mut() {
+                    Some(log_file) if entry.level <= file_level => log_file,
+                    _ => continue,
+                };
+                match write!(log_file, "{}", entry.text) {
+                    Ok(()) => {}
                     Err(e) => {
                         print!(
                             "{}\t{}\n{}\n\n",
@@ -124,7 +328,7 @@ impl Logger {
                     }
                 };
             }
-            match log_file.flush() {
+            match log_file.map_or(Ok(()), |mut log_file| log_file.flush()) {
                 Ok(()) => self.clear_log_buffer(),
                 Err(e) => {
                     print!(
@@ -139,6 +343,22 @@ impl Logger {
         }
     }
 
+    /**
+     * Returns the number of paths with an error logged that have not been transferred since.
+     */
+    pub fn flagged_count(&self) -> usize {
+        self.flagged_paths.len()
+    }
+
+    /**
+     * Takes over the flagged paths and the last error of 'other', e.g. when the configuration is
+     * reloaded.
+     */
+    pub fn keep_state(&mut self, other: &mut Logger) {
+        self.flagged_paths = std::mem::take(&mut other.flagged_paths);
+        self.last_error = other.last_error.take();
+    }
+
     pub fn remove_flagged_path(&mut self, path: PathBuf) {
         if self.flagged_paths.contains(&path) {
             if let Some(index) = self.flagged_paths.iter().position(|x| x == &path) {
@@ -147,6 +367,48 @@ impl Logger {
         }
     }
 
+    /**
+     * Sends the buffered entries to every extra sink that accepts their level. A failing sink is
+     * reported once on the console until it works again, and never stops the program.
+     */
+    fn send_to_sinks(&mut self) {
+        for sink in self.sinks.iter_mut() {
+            let name = sink.name();
+            let level = sink.level();
+            for entry in self.entries.iter().filter(|e| e.level <= level) {
+                match sink.send(entry.level, &entry.msg, &entry.fields) {
+                    Ok(()) => self.failed_sinks.retain(|failed| failed != &name),
+                    Err(e) => {
+                        if !self.failed_sinks.contains(&name) {
+                            print!(
+                                "{}\tUnable to send log entries to {}. Error Message: {}\n\n",
+                                offset::Local::now().naive_local().format("%F %I:%M:%S%P"),
+                                name,
+                                e
+                            );
+                            self.failed_sinks.push(name.clone());
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /**
+     * Applies the retention settings to the log directory. Anything done is logged.
+     */
+    fn clean_logs(&mut self) {
+        if !self.directory.exists() {
+            return;
+        }
+        let today = offset::Local::now().date_naive();
+        let current = self.get_log_path();
+        for (level, msg) in self.retention.clean(&self.directory, today, &current) {
+            self.log(level, msg);
+        }
+    }
+
     fn clear_log_buffer(&mut self) {
         self.entries.clear();
     }
@@ -173,15 +435,10 @@ impl Logger {
             };
         }
 
-        match OpenOptions::new()
-            .write(true)
-            .append(true)
-            .create(true)
-            .open(&log_path)
-        {
+        match OpenOptions::new().append(true).create(true).open(&log_path) {
             Ok(file) => {
                 let file = BufWriter::new(file);
-                return Some(file);
+                Some(file)
             }
             Err(e) => {
                 let exit_code = match e.kind() {
@@ -193,14 +450,11 @@ impl Logger {
                 print!("{}\n{}\n\n", get_exit_msg(&exit_code), e); // Must use std::print
                 std::process::exit(get_exit_code(&exit_code));
             }
-        };
+        }
     }
 
     fn get_date(&self) -> String {
-        offset::Local::today()
-            .naive_local()
-            .format("%F")
-            .to_string()
+        offset::Local::now().date_naive().format("%F").to_string()
     }
 
     fn get_date_time(&self) -> String {
@@ -211,10 +465,6 @@ impl Logger {
     }
 
     fn get_log_path(&self) -> PathBuf {
-        let mut name: String = self.get_date();
-        name.push_str(".txt");
-        let mut path = self.directory.clone();
-        path.push(name.as_str());
-        path
+        self.retention.log_path(&self.directory, &self.get_date())
     }
 }
@@ -1,23 +1,95 @@
-use file_diff::*;
-use std::path::PathBuf;
+use chrono::{DateTime, Local};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
 use std::thread::sleep;
+use std::time::{Instant, SystemTime};
 use std::{env, fs, process, time};
 
 #[path = "codes.rs"]
 mod codes;
 use codes::*;
 
+#[path = "rename.rs"]
+mod rename;
+
+#[path = "sniff.rs"]
+mod sniff;
+
+#[path = "stats.rs"]
+mod stats;
+use stats::{format_bytes, Stats};
+
+#[path = "space.rs"]
+mod space;
+
+#[path = "template.rs"]
+mod template;
+
+#[path = "compare.rs"]
+mod compare;
+
+#[path = "http.rs"]
+mod http;
+
+#[path = "mounts.rs"]
+mod mounts;
+
+#[path = "metrics.rs"]
+mod metrics;
+
+#[path = "status.rs"]
+mod status;
+use status::State;
+
+#[path = "hooks.rs"]
+mod hooks;
+
+#[path = "webhook.rs"]
+mod webhook;
+
+#[path = "lock.rs"]
+mod lock;
+
+#[path = "signals.rs"]
+mod signals;
+
+#[path = "schedule.rs"]
+mod schedule;
+
+#[path = "manifest.rs"]
+mod manifest;
+
+#[path = "records.rs"]
+mod records;
+
+#[path = "fsutil.rs"]
+mod fsutil;
+
+#[path = "versions.rs"]
+mod versions;
+
+#[path = "undo.rs"]
+mod undo;
+
+#[path = "commands.rs"]
+mod commands;
+
 #[path = "options.rs"]
 mod options;
 use options::*;
 
-static VERSION: &'static str = env!("CARGO_PKG_VERSION");
+static VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn main() {
-    let mut options = Options::new(&env::args().collect());
+    let args = env::args().collect::<Vec<String>>();
+    commands::run(&args);
+    let mut options = Options::new(&args);
 
     loop {
-        sleep(time::Duration::from_secs(options.interval() as u64));
+        wait_for_next_run(&mut options);
         match move_content(&mut options) {
             Ok(()) => continue,
             Err(e) => quit(&EC::IOGeneric, &mut options, e.to_string()),
@@ -25,7 +97,93 @@ fn main() {
     }
 }
 
+/**
+ * How often commands are checked for while waiting for the next sweep.
+ */
+const POLL_INTERVAL: time::Duration = time::Duration::from_millis(100);
+
+/**
+ * Sleeps until the next sweep is due. Commands from the control endpoint and signals are handled
+ * while waiting: a sweep request ends the wait early, a reload applies the configuration again, a
+ * pause holds off sweeps until resumed and a shutdown exits.
+ */
+fn wait_for_next_run(options: &mut Options) {
+    let mut next_run = schedule_next_run(options);
+    options.update_status(State::Idle, None);
+    let mut paused = false;
+    loop {
+        if options.shutdown_requested() {
+            shutdown(options);
+        }
+        if options.take_reload_request() {
+            options.reload();
+            next_run = schedule_next_run(options);
+            options.update_status(State::Idle, None);
+        }
+        if options.paused() != paused {
+            paused = !paused;
+            if paused {
+                options.logger().log_and_flush("Paused.");
+                options.set_next_run(None);
+                options.update_status(State::Paused, None);
+            } else {
+                options.logger().log_and_flush("Resumed.");
+                let remaining = next_run.saturating_duration_since(Instant::now());
+                options.set_next_run(Some(Local::now() + remaining));
+                options.update_status(State::Idle, None);
+            }
+        }
+        if options.take_sweep_request() && !paused {
+            break;
+        }
+        let now = Instant::now();
+        if now >= next_run && !paused {
+            break;
+        }
+        sleep(
+            POLL_INTERVAL.min(
+                next_run
+                    .saturating_duration_since(now)
+                    .max(time::Duration::from_millis(1)),
+            ),
+        );
+    }
+    options.set_next_run(None);
+}
+
+/**
+ * Works out when the next sweep is due from the interval, cron expression and active windows and
+ * logs it. Times from the cron expression or a window opening are logged as info, the regular
+ * interval only as trace.
+ */
+fn schedule_next_run(options: &mut Options) -> Instant {
+    let now = Local::now();
+    let interval = time::Duration::from_secs(options.interval() as u64);
+    let (next, scheduled) = match options.schedule().next_run(now, interval) {
+        Some(next) => next,
+        None => {
+            let msg = "The schedule given with /cron and /window never runs.";
+            quit(&EC::SuppliedArgs, options, msg);
+            (now, false)
+        }
+    };
+    options.set_next_run(Some(next));
+    let msg = format!("Next run at {}.", next.format("%F %H:%M:%S %:z"));
+    if scheduled {
+        options.logger().log_and_flush(msg);
+    } else {
+        options.logger().log_trace(msg);
+    }
+    Instant::now() + (next - now).to_std().unwrap_or_default()
+}
+
 fn move_content(options: &mut Options) -> Result<(), std::io::Error> {
+    let cycle_start = Instant::now();
+    let cycle_started_at = Local::now();
+    *options.stats() = Stats::default();
+    options.recheck_mounts();
+    options.update_status(State::Scanning, None);
+
     // Create a list of directories to be moved. Add to this list recursively.
     let mut directories = Vec::new();
     // The user supplied root directory will be the first directory added.
@@ -38,17 +196,33 @@ fn move_content(options: &mut Options) -> Result<(), std::io::Error> {
     // Subdirectories that will need to be removed if /copy is not specified.
     let mut sub_dirs = Vec::<PathBuf>::new();
 
+    // False once a missing path cuts the sweep short (see /missing).
+    let mut complete = true;
+
+    // The file each destination was claimed by this cycle. With /flatten, /template or /rename two
+    // files can end up with the same destination, and only the first one found is transferred.
+    let mut claimed = HashMap::<PathBuf, PathBuf>::new();
+    let warned = std::mem::take(options.collisions());
+
     // Recursively process the directories added to the list.
-    while let Some(working_path) = directories.pop() {
+    'directories: while let Some(working_path) = directories.pop() {
         // Paths need to be validated every iteration at the beginning of the directories loop, and
         // at the beginning of the files loop.
-        options.validate_paths();
+        if !options.validate_paths() {
+            complete = false;
+            break;
+        }
 
-        // Use 'from_dir_parent_level' to make a relative path like "..\child\dir"
-        let child_dir: PathBuf = working_path
-            .components()
-            .skip(from_dir_parent_level)
-            .collect();
+        // Use 'from_dir_parent_level' to make a relative path like "..\child\dir". When /flatten is
+        // specified, every file is treated as if it were directly inside the 'from' directory.
+        let child_dir: PathBuf = if options.flatten() {
+            PathBuf::new()
+        } else {
+            working_path
+                .components()
+                .skip(from_dir_parent_level)
+                .collect()
+        };
 
         // Create the 'to' directory path.
         let to_dir = if child_dir.components().count() == 0 {
@@ -57,42 +231,116 @@ fn move_content(options: &mut Options) -> Result<(), std::io::Error> {
             options.to_dir().join(&child_dir)
         };
 
-        // Create the 'to' directory on the file system.
-        if fs::metadata(&to_dir).is_err() {
+        // Create the 'to' directory on the file system. Templated destinations create their own
+        // directories as files are copied.
+        if options.template().is_none() && fs::metadata(&to_dir).is_err() {
             match fs::create_dir_all(&to_dir) {
                 Ok(()) => {}
                 Err(e) => quit(&EC::ToDirCreation, options, e.to_string()),
             };
         }
 
+        options
+            .logger()
+            .log_trace(format!("Scanning \"{}\".", working_path.to_str().unwrap()));
+
         // Attempt to copy all of the files within the current directory. If /copy is specified, the
         // files will not be deleted after successful copies. Add any sub directories to our
         // directories list to be processed.
         for entry in fs::read_dir(&working_path)? {
-            options.validate_paths(); // Also needed here.
+            // Also needed here.
+            if !options.validate_paths() {
+                complete = false;
+                break 'directories;
+            }
+            // A shutdown signal takes effect between files, so no file is left half copied.
+            if options.shutdown_requested() {
+                shutdown(options);
+            }
             let entry = entry?;
             let from_path = entry.path();
-            if from_path.is_dir() {
+            if options.is_disposed(&from_path) {
+                // Is the trash directory or a file renamed by /disposal.
+                continue;
+            } else if from_path.is_dir() {
                 // Is a directory.
                 directories.push(from_path.clone());
                 if !options.copy() {
                     sub_dirs.push(from_path);
                 }
+            } else if options.is_lock_file(&from_path) {
+                // Is the lock file of this job.
+                continue;
             } else {
                 // Is a file.
                 if let Some(filename) = from_path.file_name() {
-                    let to_path = to_dir.join(filename);
-                    // This function name isn't good. Should read, if not identical -> do something.
-                    // Don't want to keep copying files that are identical.
-                    if !diff(from_path.to_str().unwrap(), to_path.to_str().unwrap()) {
-                        if to_path.exists() {
-                            // The file exists, but contain different contents. Refer to the /o option.
-                            if options.overwrite() {
-                                copy_file(&from_path, &to_path, options);
-                            }
-                        } else {
-                            copy_file(&from_path, &to_path, options);
+                    let content_type = if options.sniff() {
+                        sniff::detect(&from_path)
+                    } else {
+                        None
+                    };
+                    if !options.type_allowed(content_type) {
+                        options.logger().log_debug(format!(
+                            "Skipped \"{}\". Its content type ({}) is not listed in /types.",
+                            from_path.to_str().unwrap(),
+                            content_type.unwrap_or("unknown")
+                        ));
+                        continue;
+                    }
+                    // Files whose extension does not match their content go to the review
+                    // directory instead, if there is one.
+                    let mismatch = options.review_dir().is_some()
+                        && sniff::is_mismatch(&from_path, content_type);
+                    let to_path = if mismatch {
+                        review_path(options, &child_dir, filename)
+                    } else {
+                        let content_type = content_type.unwrap_or("unknown");
+                        destination_path(
+                            options,
+                            &to_dir,
+                            &child_dir,
+                            &from_path,
+                            filename,
+                            content_type,
+                        )
+                    };
+                    if let Some(first) = claimed.get(&to_path) {
+                        log_collision(options, &from_path, first, &to_path, &warned);
+                        continue;
+                    }
+                    claimed.insert(to_path.clone(), from_path.clone());
+                    // Don't want to keep copying files that are identical. If the file exists, but
+                    // contains different contents, refer to the /o option.
+                    let copy = if !to_path.exists() {
+                        true
+                    } else if options.comparer().identical(&from_path, &to_path) {
+                        options.logger().log_debug(format!(
+                            "Skipped \"{}\". It is identical to \"{}\".",
+                            from_path.to_str().unwrap(),
+                            to_path.to_str().unwrap()
+                        ));
+                        options.stats().identical += 1;
+                        false
+                    } else if !options.overwrite() {
+                        options.logger().log_debug(format!(
+                            "Skipped \"{}\". \"{}\" already exists with different contents and /o is not specified.",
+                            from_path.to_str().unwrap(),
+                            to_path.to_str().unwrap()
+                        ));
+                        options.stats().blocked += 1;
+                        false
+                    } else {
+                        true
+                    };
+                    if copy
+                        && has_room(options, &from_path, &to_path)
+                        && before_transfer(options, &from_path, &to_path)
+                        && keep_version(options, &to_path)
+                    {
+                        if mismatch {
+                            log_mismatch(options, &from_path, content_type);
                         }
+                        copy_file(&from_path, &to_path, options);
                     }
                 }
             }
@@ -102,73 +350,961 @@ fn move_content(options: &mut Options) -> Result<(), std::io::Error> {
         options.logger().flush_logs();
     }
 
+    if complete {
+        options.comparer().prune();
+    }
+
     // Remove empty directories.
-    if !sub_dirs.is_empty() {
-        sub_dirs.reverse();
-        for dir in sub_dirs {
-            let dir_str = dir.to_str().unwrap();
-            match fs::remove_dir(&dir) {
-                Ok(()) => options.logger().remove_flagged_path(dir),
-                Err(e) => options.logger().log_error(
-                    dir.clone(),
-                    format!(
-                        "Attempted to remove the directory at \"{}\". Error Message: {}",
-                        dir_str, e
-                    ),
+    if complete {
+        remove_dirs(options, sub_dirs);
+        purge_trash(options);
+    }
+    write_manifest(options, cycle_started_at, complete);
+
+    finish_cycle(options, cycle_start);
+    options.update_status(State::Idle, None);
+    Ok(())
+}
+
+/**
+ * Removes the emptied subdirectories of the 'from' directory, deepest first, as /dirs allows, and
+ * then the 'from' directory itself with /removeroot. Directories that still hold something are
+ * only reported as debug messages, since files may still be arriving.
+ */
+fn remove_dirs(options: &mut Options, mut sub_dirs: Vec<PathBuf>) {
+    // Nothing is removed when copying, and keep also keeps the 'from' directory.
+    if options.copy() || matches!(options.dirs(), DirPolicy::Keep) {
+        return;
+    }
+    sub_dirs.reverse();
+    if options.remove_root() {
+        sub_dirs.push(options.from_dir());
+    }
+    for dir in sub_dirs {
+        let dir_str = dir.to_str().unwrap();
+        if options.is_protected(&dir) {
+            options
+                .logger()
+                .log_trace(format!("Kept \"{}\". It is protected.", dir_str));
+            continue;
+        }
+        if let DirPolicy::EmptyFor(secs) = options.dirs() {
+            match empty_for(&dir) {
+                Some(empty) if empty.as_secs() >= secs => {}
+                Some(empty) => {
+                    options.logger().log_debug(format!(
+                        "Kept \"{}\". It has only been empty for {}s.",
+                        dir_str,
+                        empty.as_secs()
+                    ));
+                    continue;
+                }
+                None => {
+                    options
+                        .logger()
+                        .log_debug(format!("Kept \"{}\". It is not empty.", dir_str));
+                    continue;
+                }
+            }
+        }
+        match fs::remove_dir(&dir) {
+            Ok(()) => {
+                options.record(json!({
+                    "action": "removed_dir",
+                    "path": records::path_value(&dir),
+                }));
+                options.logger().remove_flagged_path(dir)
+            }
+            Err(e) if e.kind() == ErrorKind::DirectoryNotEmpty => options
+                .logger()
+                .log_debug(format!("Kept \"{}\". It is not empty.", dir_str)),
+            Err(e) => options.logger().log_error(
+                dir.clone(),
+                format!(
+                    "Attempted to remove the directory at \"{}\". Error Message: {}",
+                    dir_str, e
                 ),
+            ),
+        }
+    }
+}
+
+/**
+ * How long a directory has been empty, going by its modified time, which changes whenever an entry
+ * is added or removed. None if it is not empty.
+ */
+fn empty_for(dir: &Path) -> Option<time::Duration> {
+    if fs::read_dir(dir).ok()?.next().is_some() {
+        return None;
+    }
+    let mtime = fs::metadata(dir).ok()?.modified().ok()?;
+    Some(SystemTime::now().duration_since(mtime).unwrap_or_default())
+}
+
+/**
+ * Adds the cycle to the totals since start up and logs a summary if anything was transferred or
+ * failed.
+ */
+fn finish_cycle(options: &mut Options, cycle_start: Instant) {
+    let mut stats = options.stats().clone();
+    stats.cycles = 1;
+    stats.duration = cycle_start.elapsed();
+    options.totals().add(&stats);
+    let retry_queue = options.logger().flagged_count();
+    if let Some(metrics) = options.metrics() {
+        metrics.lock().unwrap().record_cycle(&stats, retry_queue);
+    }
+    if !stats.is_empty() {
+        let totals = options.totals().clone();
+        let msg = format!(
+            "Cycle summary: {}.\nSince start up: {} over {} cycles.",
+            stats.describe(),
+            totals.describe(),
+            totals.cycles
+        );
+        let fields = vec![
+            ("FILES", (stats.moved + stats.copied).to_string()),
+            ("BYTES", stats.bytes.to_string()),
+            ("FAILED", stats.failed.to_string()),
+            ("DURATION_MS", stats.duration.as_millis().to_string()),
+        ];
+        options.logger().log_fields(Level::Info, msg, fields);
+        on_cycle_complete(options, &stats);
+        options.notify(
+            "summary",
+            json!({
+                "moved": stats.moved,
+                "copied": stats.copied,
+                "bytes": stats.bytes,
+                "identical": stats.identical,
+                "blocked": stats.blocked,
+                "failed": stats.failed,
+                "duration_ms": stats.duration.as_millis() as u64,
+            }),
+        );
+    }
+    options.logger().flush_logs();
+}
+
+/**
+ * Builds the destination path of a file, applying any rename rules to its name. Without a template
+ * this mirrors the file's location under the 'from' directory. Templated destinations have their
+ * parent directories created here.
+ */
+fn destination_path(
+    options: &mut Options,
+    to_dir: &Path,
+    child_dir: &Path,
+    from_path: &Path,
+    filename: &OsStr,
+    content_type: &str,
+) -> PathBuf {
+    let file_name = if options.rename().is_empty() {
+        filename.to_string_lossy().to_string()
+    } else {
+        options.rename().apply(&filename.to_string_lossy())
+    };
+    let template = match options.template() {
+        Some(template) => template.to_string(),
+        None => return to_dir.join(file_name),
+    };
+    let mtime = match fs::metadata(from_path).and_then(|m| m.modified()) {
+        Ok(mtime) => mtime,
+        Err(_) => SystemTime::now(),
+    };
+    let ctx = template::Context {
+        to_dir: &options.to_dir(),
+        child_dir,
+        file_name: &file_name,
+        mtime,
+        now: Local::now(),
+        label: options.label(),
+        content_type,
+        hostname: options.hostname(),
+    };
+    let to_path = match template::render(&template, &ctx) {
+        Ok(to_path) => to_path,
+        Err(e) => {
+            quit(&EC::SuppliedArgs, options, e);
+            PathBuf::new()
+        }
+    };
+    create_parent_dir(options, &to_path);
+    to_path
+}
+
+/**
+ * Builds the path of a file in the review directory, keeping its path relative to the 'from'
+ * directory and its original name.
+ */
+fn review_path(options: &mut Options, child_dir: &Path, filename: &OsStr) -> PathBuf {
+    let to_path = options.review_dir().unwrap().join(child_dir).join(filename);
+    create_parent_dir(options, &to_path);
+    to_path
+}
+
+fn create_parent_dir(options: &mut Options, path: &Path) {
+    if let Some(parent) = path.parent() {
+        if fs::metadata(parent).is_err() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                quit(&EC::ToDirCreation, options, e.to_string());
             }
         }
     }
+}
 
-    Ok(())
+/**
+ * Logs a file skipped because another file found this cycle goes to the same path. It is a
+ * warning the first time, and a debug message while it keeps happening.
+ */
+fn log_collision(
+    options: &mut Options,
+    from_path: &Path,
+    first: &Path,
+    to_path: &Path,
+    warned: &HashSet<PathBuf>,
+) {
+    let msg = format!(
+        "Skipped \"{}\". \"{}\" was found first and goes to the same path \"{}\".",
+        from_path.to_str().unwrap(),
+        first.to_str().unwrap(),
+        to_path.to_str().unwrap()
+    );
+    if warned.contains(from_path) {
+        options.logger().log_debug(msg);
+    } else {
+        options.logger().log(Level::Warn, msg);
+    }
+    options.collisions().insert(from_path.to_path_buf());
+}
+
+fn log_mismatch(options: &mut Options, from_path: &Path, content_type: Option<&str>) {
+    let msg = format!(
+        "Content mismatch: \"{}\" is named like a {} file but contains {}. Sending it to the review directory.",
+        from_path.to_str().unwrap(),
+        sniff::expected_type(from_path).unwrap_or("unknown"),
+        content_type.unwrap_or("unknown")
+    );
+    options.logger().log(Level::Warn, msg);
+}
+
+/**
+ * Moves a file about to be overwritten into its versions directory with /versions, removing the
+ * versions beyond the newest N. Returns false if the file could not be kept, so it is not
+ * overwritten.
+ */
+fn keep_version(options: &mut Options, to_path: &Path) -> bool {
+    if !to_path.exists() {
+        return true;
+    }
+    let len = fs::metadata(to_path).map_or(0, |m| m.len());
+    let result = match options.versions() {
+        Some((versions, keep)) => versions
+            .keep(to_path)
+            .and_then(|kept| Ok((kept, versions.prune(to_path, keep)?))),
+        None => return true,
+    };
+    let to_path_str = to_path.to_str().unwrap();
+    match result {
+        Ok((kept, removed)) => {
+            // A version kept outside the 'to' directory gives its space back to the /quota, and so
+            // do the versions removed inside it.
+            let freed = if versions_in_to_dir(options) {
+                removed.iter().map(|(_, len)| len).sum()
+            } else {
+                len
+            };
+            *options.quota_used() -= freed.min(*options.quota_used());
+            options.record(json!({
+                "action": "versioned",
+                "path": records::path_value(to_path),
+                "version": records::path_value(&kept),
+            }));
+            options.logger().log_debug(format!(
+                "Kept the old version of \"{}\" as \"{}\".",
+                to_path_str,
+                kept.to_str().unwrap()
+            ));
+            for (path, _) in removed {
+                options.logger().log_debug(format!(
+                    "Removed the old version \"{}\".",
+                    path.to_str().unwrap()
+                ));
+            }
+            true
+        }
+        Err(e) => {
+            options.stats().failed += 1;
+            options.logger().log_error(
+                to_path.to_path_buf(),
+                format!(
+                    "Attempted to keep the old version of \"{}\". Error Message: {}",
+                    to_path_str, e
+                ),
+            );
+            false
+        }
+    }
 }
 
 /**
  * Attempts to copy a file. Any errors are logged to a buffer inside of Options::Logger. If /copy is
  * specified, try to delete the original afterwards.
  */
-fn copy_file(from_path: &PathBuf, to_path: &PathBuf, options: &mut Options) {
+fn copy_file(from_path: &Path, to_path: &Path, options: &mut Options) {
     let from_path_str = from_path.to_str().unwrap();
-    match fs::copy(from_path, to_path) {
-        Ok(_file_size) => {
+    let size = fs::metadata(from_path).map_or(0, |m| m.len());
+    // Anything still at 'to_path' is overwritten, unless /versions moved it away already.
+    let replaced = fs::metadata(to_path).map_or(0, |m| m.len());
+    options.update_status(State::Transferring, Some((from_path, size)));
+    let started = Instant::now();
+    let result = fs::copy(from_path, to_path);
+    options.update_status(State::Scanning, None);
+    match result {
+        Ok(file_size) => {
+            let used = options.quota_used();
+            *used = (*used + file_size).saturating_sub(replaced);
+            // Keep the modification time so the copies compare as identical next time.
+            if let Err(e) = copy_mtime(from_path, to_path) {
+                options.logger().log_error(
+                    to_path.to_path_buf(),
+                    format!(
+                        "Attempted to set the modified time of \"{}\". Error Message: {}",
+                        to_path.to_str().unwrap(),
+                        e
+                    ),
+                );
+            }
             if !options.copy() {
                 // Copy complete, try to remove it and then log it.
-                remove_file(options, from_path, to_path);
+                dispose_file(options, from_path, to_path, file_size);
             } else {
                 // Copy complete, log it.
-                options
-                    .logger()
-                    .log_transfer(from_path_str, to_path.to_str().unwrap(), true);
+                options.stats().copied += 1;
+                options.stats().bytes += file_size;
+                let renamed = renamed_to(from_path, to_path);
+                options.record(json!({
+                    "action": "copied",
+                    "from": records::path_value(from_path),
+                    "to": records::path_value(to_path),
+                    "bytes": file_size,
+                    "renamed": renamed,
+                }));
+                options.logger().log_transfer(
+                    from_path_str,
+                    to_path.to_str().unwrap(),
+                    true,
+                    file_size,
+                    renamed,
+                );
+                after_transfer(options, from_path, to_path, true, file_size);
+            }
+            pace(options, file_size, started.elapsed());
+        }
+        Err(e) => {
+            options.stats().failed += 1;
+            options.record(json!({
+                "action": "failed",
+                "from": records::path_value(from_path),
+                "to": records::path_value(to_path),
+                "error": e.to_string(),
+            }));
+            options.logger().log_error(
+                from_path.to_path_buf(),
+                format!(
+                    "Attempted to copy the file at \"{}\". Error Message: {}",
+                    from_path_str, e
+                ),
+            )
+        }
+    }
+}
+
+/**
+ * Waits after a transfer for as long as it takes to bring the average rate down to the rate limit,
+ * see /rate and /window. A shutdown signal still takes effect while waiting.
+ */
+fn pace(options: &mut Options, bytes: u64, took: time::Duration) {
+    let rate = match options.rate() {
+        Some(rate) => rate,
+        None => return,
+    };
+    let until = Instant::now() + schedule::transfer_time(bytes, rate).saturating_sub(took);
+    loop {
+        if options.shutdown_requested() {
+            shutdown(options);
+        }
+        let now = Instant::now();
+        if now >= until {
+            return;
+        }
+        sleep(POLL_INTERVAL.min(until - now));
+    }
+}
+
+fn copy_mtime(from_path: &Path, to_path: &Path) -> std::io::Result<()> {
+    let mtime = fs::metadata(from_path)?.modified()?;
+    fs::OpenOptions::new()
+        .write(true)
+        .open(to_path)?
+        .set_modified(mtime)
+}
+
+/**
+ * The new name of a file if it was delivered under a different name than it had. Files sent to the
+ * review directory keep their name, whatever the rename rules say.
+ */
+fn renamed_to(from_path: &Path, to_path: &Path) -> Option<String> {
+    let name = from_path.file_name()?;
+    let delivered = to_path.file_name()?;
+    if delivered != name {
+        Some(delivered.to_string_lossy().to_string())
+    } else {
+        None
+    }
+}
+
+/**
+ * Attempts to dispose of the original of a moved file, as /disposal says: delete it, move it into
+ * the trash or rename it. This should be used after a successful copy.
+ * If any errors are encountered, log it and keep running.
+ */
+fn dispose_file(options: &mut Options, from_path: &Path, to_path: &Path, file_size: u64) {
+    let from_path_str = from_path.to_str().unwrap();
+    let result = match options.disposal() {
+        Disposal::Delete => fs::remove_file(from_path).map(|()| None),
+        Disposal::Trash(trash_dir) => {
+            let trash_dir = trash_dir.clone();
+            trash_file(options, from_path, &trash_dir).map(Some)
+        }
+        Disposal::Suffix(suffix) => {
+            let renamed = PathBuf::from(format!("{}{}", from_path_str, suffix));
+            fs::rename(from_path, &renamed).map(|()| Some(renamed))
+        }
+    };
+    match result {
+        Ok(kept) => {
+            options.stats().moved += 1;
+            options.stats().bytes += file_size;
+            let renamed = renamed_to(from_path, to_path);
+            options.record(json!({
+                "action": "moved",
+                "from": records::path_value(from_path),
+                "to": records::path_value(to_path),
+                "bytes": file_size,
+                "kept": kept.as_deref().map(records::path_value),
+                "renamed": renamed,
+            }));
+            options.logger().log_transfer(
+                from_path_str,
+                to_path.to_str().unwrap(),
+                false,
+                file_size,
+                renamed,
+            );
+            if let Some(kept) = kept {
+                options.logger().log_debug(format!(
+                    "Kept the original of \"{}\" as \"{}\".",
+                    from_path_str,
+                    kept.to_str().unwrap()
+                ));
+            }
+            after_transfer(options, from_path, to_path, false, file_size);
+        }
+        Err(e) => {
+            options.stats().failed += 1;
+            options.record(json!({
+                "action": "failed",
+                "from": records::path_value(from_path),
+                "to": records::path_value(to_path),
+                "error": e.to_string(),
+            }));
+            options.logger().log_error(
+                from_path.to_path_buf(),
+                format!(
+                    "Attempted to remove the file at \"{}\". Error Message: {}",
+                    from_path_str, e
+                ),
+            )
+        }
+    }
+}
+
+/**
+ * Moves a file into the trash directory for today, keeping its path relative to the 'from'
+ * directory and adding the time to its name, e.g. "trash/2024-05-01/sub/report.142501.csv".
+ */
+fn trash_file(
+    options: &mut Options,
+    from_path: &Path,
+    trash_dir: &Path,
+) -> std::io::Result<PathBuf> {
+    let now = Local::now();
+    let from_dir = options.from_dir();
+    let relative = from_path.strip_prefix(&from_dir).unwrap_or(from_path);
+    let mut dir = trash_dir.join(now.format("%Y-%m-%d").to_string());
+    if let Some(parent) = relative.parent() {
+        dir.push(parent);
+    }
+    fs::create_dir_all(&dir)?;
+    let stem = from_path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = match from_path.extension() {
+        Some(ext) => format!(".{}", ext.to_string_lossy()),
+        None => String::new(),
+    };
+    let time = now.format("%H%M%S");
+    let mut path = dir.join(format!("{}.{}{}", stem, time, ext));
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{}.{}-{}{}", stem, time, n, ext));
+        n += 1;
+    }
+    fsutil::move_file(from_path, &path)?;
+    Ok(path)
+}
+
+/**
+ * Removes the days of trash older than /trashdays.
+ */
+fn purge_trash(options: &mut Options) {
+    let (trash_dir, days) = match (options.disposal(), options.trash_days()) {
+        (Disposal::Trash(dir), Some(days)) => (dir.clone(), days),
+        _ => return,
+    };
+    let oldest_kept = Local::now().date_naive() - chrono::Duration::days(days as i64 - 1);
+    let entries = match fs::read_dir(&trash_dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let date = match chrono::NaiveDate::parse_from_str(&name, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => continue,
+        };
+        if date >= oldest_kept {
+            continue;
+        }
+        let path = entry.path();
+        match fs::remove_dir_all(&path) {
+            Ok(()) => options
+                .logger()
+                .log_info(format!("Purged the trash from {}.", name)),
+            Err(e) => options.logger().log_error(
+                path.clone(),
+                format!(
+                    "Attempted to purge the trash at \"{}\". Error Message: {}",
+                    path.to_str().unwrap(),
+                    e
+                ),
+            ),
+        }
+    }
+}
+
+/**
+ * Sends the transfer webhook and runs the on_file_transferred hook for a file that was moved or
+ * copied.
+ */
+fn after_transfer(options: &mut Options, from_path: &Path, to_path: &Path, copy: bool, bytes: u64) {
+    add_to_manifest(options, to_path, bytes);
+    if let Some(command) = options.hooks().on_file.clone() {
+        let mut env = hook_env(options, from_path, to_path, to_path);
+        env.push((
+            "MOVER_MODE",
+            String::from(if copy { "copy" } else { "move" }),
+        ));
+        run_hook(options, "on_file_transferred", &command, &env, false);
+    }
+    options.notify(
+        "transfer",
+        json!({
+            "source": from_path.to_str().unwrap(),
+            "dest": to_path.to_str().unwrap(),
+            "bytes": bytes,
+            "mode": if copy { "copy" } else { "move" },
+        }),
+    );
+}
+
+/**
+ * Adds a file delivered to the 'to' directory to the manifest with /manifest. Files sent to the
+ * review directory are left out.
+ */
+fn add_to_manifest(options: &mut Options, to_path: &Path, bytes: u64) {
+    let to_dir = options.to_dir();
+    let relative = match (options.manifest(), to_path.strip_prefix(&to_dir)) {
+        (Some(_), Ok(relative)) => relative.to_string_lossy().to_string(),
+        _ => return,
+    };
+    let result = compare::sha256(to_path).and_then(|sha256| {
+        let entry = manifest::Entry {
+            path: relative,
+            bytes,
+            sha256,
+            delivered: Local::now(),
+        };
+        options.manifest().unwrap().add(&to_dir, entry)
+    });
+    if let Err(e) = result {
+        options.manifest().unwrap().miss();
+        options.logger().log_error(
+            to_path.to_path_buf(),
+            format!(
+                "Attempted to add \"{}\" to the manifest. Error Message: {}",
+                to_path.to_str().unwrap(),
+                e
+            ),
+        );
+    }
+}
+
+/**
+ * Writes the manifest of the files delivered this cycle with /manifest, and the success marker
+ * with /success if the cycle completed without errors.
+ */
+fn write_manifest(options: &mut Options, cycle_started_at: DateTime<Local>, complete: bool) {
+    let clean = complete && options.stats().failed == 0;
+    let to_dir = options.to_dir();
+    let result = match options.manifest() {
+        Some(manifest) => manifest.finish(&to_dir, cycle_started_at, clean),
+        None => return,
+    };
+    match result {
+        Ok(written) => {
+            options.logger().remove_flagged_path(to_dir);
+            for path in written {
+                options.logger().log_debug(format!(
+                    "Wrote the manifest \"{}\".",
+                    path.to_str().unwrap()
+                ));
             }
         }
         Err(e) => options.logger().log_error(
-            from_path.clone(),
+            to_dir.clone(),
             format!(
-                "Attempted to copy the file at \"{}\". Error Message: {}",
-                from_path_str, e
+                "Attempted to write the manifest in \"{}\". Error Message: {}",
+                to_dir.to_str().unwrap(),
+                e
             ),
         ),
     }
 }
 
 /**
- * Attempts to remove a file. This should be used after a successful copy.
- * If any errors are encountered, log it and keep running.
+ * Checks that the destination has room for a file: free space for the file and the /reserve, and
+ * room under the /quota, evicting the oldest files first with /quotamode evict. Returns false if
+ * the file has to wait. Files that do not fit are skipped, so smaller files can still go.
  */
-fn remove_file(options: &mut Options, from_path: &PathBuf, to_path: &PathBuf) {
-    let from_path_str = from_path.to_str().unwrap();
-    match fs::remove_file(from_path) {
-        Ok(()) => options
+fn has_room(options: &mut Options, from_path: &Path, to_path: &Path) -> bool {
+    let needed = quota_needed(options, from_path, to_path);
+    let reserve = options.reserve();
+    let mut problem = None;
+    match space::available(to_path.parent().unwrap_or(to_path)) {
+        Ok(available) if available < needed.saturating_add(reserve) => {
+            problem = Some(format!(
+                "it needs {} and {} is free with {} reserved",
+                format_bytes(needed as f64),
+                format_bytes(available as f64),
+                format_bytes(reserve as f64)
+            ));
+        }
+        Ok(_) => {}
+        Err(e) => options.logger().log_debug(format!(
+            "Unable to check the free space for \"{}\". Error Message: {}",
+            to_path.to_str().unwrap(),
+            e
+        )),
+    }
+    if let (None, Some((quota, evict))) = (&problem, options.quota()) {
+        let used = *options.quota_used();
+        if used + needed > quota && evict {
+            evict_oldest(options, used + needed - quota, to_path);
+        }
+        let used = *options.quota_used();
+        if used + needed > quota {
+            problem = Some(format!(
+                "it needs {} and the /quota of {} has {} left",
+                format_bytes(needed as f64),
+                format_bytes(quota as f64),
+                format_bytes(quota.saturating_sub(used) as f64)
+            ));
+        }
+    }
+    let msg = match problem {
+        Some(problem) => format!(
+            "Skipped \"{}\". There is not enough room in the 'to' path, {}.",
+            from_path.to_str().unwrap(),
+            problem
+        ),
+        None => {
+            if options.set_short_of_space(false) {
+                options
+                    .logger()
+                    .log_info("There is room in the 'to' path again, resuming transfers...\n");
+            }
+            return true;
+        }
+    };
+    if options.set_short_of_space(true) {
+        options.logger().log(
+            Level::Warn,
+            format!("{}\nPausing transfers that do not fit...\n", msg),
+        );
+    } else {
+        options.logger().log_debug(msg);
+    }
+    false
+}
+
+/**
+ * How many more bytes the 'to' directory holds once a file is transferred. Overwriting a file gives
+ * back the space it takes, unless /versions keeps it inside the 'to' directory.
+ */
+fn quota_needed(options: &Options, from_path: &Path, to_path: &Path) -> u64 {
+    let size = fs::metadata(from_path).map_or(0, |m| m.len());
+    let replaced = if versions_in_to_dir(options) {
+        0
+    } else {
+        fs::metadata(to_path).map_or(0, |m| m.len())
+    };
+    size.saturating_sub(replaced)
+}
+
+/**
+ * Returns true if /versions keeps old versions inside the 'to' directory, where they count
+ * towards the /quota.
+ */
+fn versions_in_to_dir(options: &Options) -> bool {
+    options.versions().is_some_and(|(versions, _)| {
+        versions.root.is_none_or(|root| {
+            std::path::absolute(root)
+                .unwrap_or(root.to_path_buf())
+                .starts_with(std::path::absolute(options.to_dir()).unwrap_or(options.to_dir()))
+        })
+    })
+}
+
+/**
+ * Removes the oldest files in the 'to' directory until 'bytes' have been freed. 'keep' and the
+ * files mover keeps there itself are never removed.
+ */
+fn evict_oldest(options: &mut Options, bytes: u64, keep: &Path) {
+    let mut freed = 0;
+    for (path, _, len) in space::oldest_files(&options.to_dir()) {
+        if freed >= bytes {
+            break;
+        }
+        if path == keep || is_mover_file(options, &path) {
+            continue;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                freed += len;
+                *options.quota_used() -= len.min(*options.quota_used());
+                options.record(json!({
+                    "action": "evicted",
+                    "path": records::path_value(&path),
+                    "bytes": len,
+                }));
+                options.logger().log_info(format!(
+                    "Evicted \"{}\" ({}) to stay under the /quota.",
+                    path.to_str().unwrap(),
+                    format_bytes(len as f64)
+                ));
+            }
+            Err(e) => options.logger().log_error(
+                path.clone(),
+                format!(
+                    "Attempted to evict the file at \"{}\". Error Message: {}",
+                    path.to_str().unwrap(),
+                    e
+                ),
+            ),
+        }
+    }
+}
+
+/**
+ * Returns true for the files mover keeps in the 'to' directory itself: the lock file, manifests,
+ * the success marker, old versions, and the trash and review directories if they are inside it.
+ */
+fn is_mover_file(options: &Options, path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    // Manifests and the marker are written under a temporary ".<name>.tmp" name first.
+    let name = name.trim_start_matches('.');
+    if options.is_lock_file(path)
+        || name.starts_with(manifest::MANIFEST_PREFIX)
+        || name.starts_with(manifest::SUCCESS_MARKER)
+        || path
+            .components()
+            .any(|c| c.as_os_str() == versions::VERSIONS_DIR)
+    {
+        return true;
+    }
+    let mut dirs = Vec::new();
+    if let Some((versions, _)) = options.versions() {
+        dirs.extend(versions.root.map(Path::to_path_buf));
+    }
+    if let Disposal::Trash(dir) = options.disposal() {
+        dirs.push(dir.clone());
+    }
+    dirs.extend(options.review_dir());
+    let path = std::path::absolute(path).unwrap_or(path.to_path_buf());
+    dirs.iter()
+        .any(|dir| path.starts_with(std::path::absolute(dir).unwrap_or(dir.clone())))
+}
+
+/**
+ * Runs the before_transfer hook, if there is one. Returns false if the hook turned the file down,
+ * failed to run or timed out. A vetoed file stays where it is and is checked again next cycle.
+ */
+fn before_transfer(options: &mut Options, from_path: &Path, to_path: &Path) -> bool {
+    let command = match options.hooks().before.clone() {
+        Some(command) => command,
+        None => return true,
+    };
+    let mtime = fs::metadata(from_path).and_then(|m| m.modified()).ok();
+    let env = hook_env(options, from_path, to_path, from_path);
+    // Only the first veto of a file is logged at the normal levels, the hook runs every cycle.
+    let quiet = options.hooks().is_vetoed(from_path);
+    let allowed = run_hook(options, "before_transfer", &command, &env, quiet)
+        .is_some_and(|outcome| outcome.success());
+    if allowed {
+        options.hooks().clear_veto(from_path);
+    } else {
+        let msg = format!(
+            "Skipped \"{}\". The before_transfer hook did not allow it.",
+            from_path.to_str().unwrap()
+        );
+        let env_value = |name| {
+            env.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.clone())
+        };
+        let size = env_value("MOVER_SIZE").and_then(|size| size.parse().ok());
+        let hashed = match (size, mtime, env_value("MOVER_HASH")) {
+            (Some(size), Some(mtime), Some(hash)) if !hash.is_empty() => Some((size, mtime, hash)),
+            _ => None,
+        };
+        if options.hooks().veto(from_path, hashed) {
+            options.logger().log(Level::Warn, msg);
+        } else {
+            options.logger().log_debug(msg);
+        }
+    }
+    allowed
+}
+
+/**
+ * Runs the on_cycle_complete hook, if there is one, with the counts of the cycle.
+ */
+fn on_cycle_complete(options: &mut Options, stats: &Stats) {
+    if let Some(command) = options.hooks().on_cycle.clone() {
+        let env = vec![
+            ("MOVER_LABEL", options.label().to_string()),
+            ("MOVER_MOVED", stats.moved.to_string()),
+            ("MOVER_COPIED", stats.copied.to_string()),
+            ("MOVER_BYTES", stats.bytes.to_string()),
+            ("MOVER_FAILED", stats.failed.to_string()),
+            ("MOVER_DURATION_MS", stats.duration.as_millis().to_string()),
+        ];
+        run_hook(options, "on_cycle_complete", &command, &env, false);
+    }
+}
+
+/**
+ * The environment passed to the file hooks. The size and SHA-256 hash are those of 'hashed_path',
+ * the source before a transfer and the destination after it.
+ */
+fn hook_env(
+    options: &mut Options,
+    from_path: &Path,
+    to_path: &Path,
+    hashed_path: &Path,
+) -> Vec<(&'static str, String)> {
+    let metadata = fs::metadata(hashed_path).ok();
+    let size = metadata.as_ref().map_or(0, |m| m.len());
+    // A vetoed file is offered to the hook every cycle, only hash it again if it changed.
+    let cached = metadata
+        .and_then(|m| m.modified().ok())
+        .and_then(|mtime| options.hooks().cached_hash(hashed_path, size, mtime));
+    let hash = match cached.map_or_else(|| compare::sha256(hashed_path), Ok) {
+        Ok(hash) => hash,
+        Err(e) => {
+            options.logger().log_debug(format!(
+                "Unable to hash \"{}\" for a hook. Error Message: {}",
+                hashed_path.to_str().unwrap(),
+                e
+            ));
+            String::new()
+        }
+    };
+    vec![
+        ("MOVER_LABEL", options.label().to_string()),
+        ("MOVER_SOURCE", from_path.to_str().unwrap().to_string()),
+        ("MOVER_DEST", to_path.to_str().unwrap().to_string()),
+        ("MOVER_SIZE", size.to_string()),
+        ("MOVER_HASH", hash),
+    ]
+}
+
+/**
+ * Runs a hook command and logs its output, a non-zero exit and a timeout. 'quiet' logs all of that
+ * as debug messages instead. Returns None if the command could not be started.
+ */
+fn run_hook(
+    options: &mut Options,
+    name: &str,
+    command: &str,
+    env: &[(&str, String)],
+    quiet: bool,
+) -> Option<hooks::Outcome> {
+    let timeout = options.hooks().timeout;
+    options
+        .logger()
+        .log_trace(format!("Running the {} hook \"{}\".", name, command));
+    let outcome = match hooks::run(command, env, timeout) {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            options.logger().log(
+                Level::Error,
+                format!(
+                    "Unable to run the {} hook \"{}\". Error Message: {}",
+                    name, command, e
+                ),
+            );
+            return None;
+        }
+    };
+    let (info, warn) = if quiet {
+        (Level::Debug, Level::Debug)
+    } else {
+        (Level::Info, Level::Warn)
+    };
+    let output = outcome.output.trim_end();
+    if !output.is_empty() {
+        options
             .logger()
-            .log_transfer(from_path_str, to_path.to_str().unwrap(), false),
-        Err(e) => options.logger().log_error(
-            from_path.clone(),
+            .log(info, format!("Output of the {} hook:\n{}", name, output));
+    }
+    match outcome.status {
+        None => options.logger().log(
+            warn,
             format!(
-                "Attempted to remove the file at \"{}\". Error Message: {}",
-                from_path_str, e
+                "The {} hook \"{}\" timed out after {}s and was stopped.",
+                name,
+                command,
+                timeout.as_secs()
             ),
         ),
+        Some(status) if !status.success() => options.logger().log(
+            warn,
+            format!("The {} hook \"{}\" failed with {}.", name, command, status),
+        ),
+        Some(_) => {}
     }
+    Some(outcome)
 }
 
 /**
@@ -191,6 +1327,18 @@ fn print_about_info() {
     println!("                                                                                                    ");
     println!("                                         Example: mover.exe \"..\\from\" \"..\\to\" /i 5            ");
     println!("                                                                                                    ");
+    println!("[/cron <expression>]          Optional - Runs sweeps at the times of a cron expression instead of   ");
+    println!("                                         every interval, e.g. \"*/15 8-18 * * MON-FRI\".              ");
+    println!("                                                                                                    ");
+    println!("[/window <HH:MM-HH:MM>]       Optional - Only runs sweeps between the given times, e.g. 22:00-06:00.");
+    println!("                                         Add =<seconds>, =<size>/s or =<seconds>,<size>/s to use a  ");
+    println!("                                         different interval or rate limit in the window. Can be     ");
+    println!("                                         given more than once. There is no concurrency setting,     ");
+    println!("                                         files are always transferred one at a time.                ");
+    println!("                                                                                                    ");
+    println!("[/rate <size>]                Optional - The most bytes per second to transfer, on average. Sizes   ");
+    println!("                                         can end with K, M or G.                                    ");
+    println!("                                                                                                    ");
     println!("[/c]                          Optional - By default, directories and their content will be copied   ");
     println!("                                         and the originals will be deleted, essentially moving them.");
     println!("                                         By using the /c option, the originals will not be deleted  ");
@@ -205,9 +1353,154 @@ fn print_about_info() {
     println!("                                         overwritten.                                               ");
     println!("                                                                                                    ");
     println!("                                         Example: mover.exe \"..\\from\" \"..\\to\" /o              ");
+    println!("                                                                                                    ");
+    println!("[/versions <n>]               Optional - With /o, keeps the last n versions of overwritten files in ");
+    println!("[/versionsdir <directory>]               a .mover-versions directory next to them, or under the     ");
+    println!("                                         given directory. Use mover.exe restore <path> /version <n> ");
+    println!("                                         to put one back, 1 being the newest.                       ");
+    println!("                                                                                                    ");
+    println!("[/compare <mode>]             Optional - How existing files are compared: size+mtime, checksum or   ");
+    println!("                                         full. The default value is size+mtime.                     ");
+    println!("                                                                                                    ");
+    println!("[/flatten]                    Optional - Places all files directly in the to-directory instead of   ");
+    println!("                                         recreating the directory structure of the from-directory.  ");
+    println!("                                         Files with the same name found in one cycle are skipped    ");
+    println!("                                         after the first, others are handled by the /o option.      ");
+    println!("                                                                                                    ");
+    println!("[/template <template>]        Optional - Builds each destination path from a template, e.g.         ");
+    println!("                                         \"{{to}}/{{yyyy}}/{{mm}}/{{dd}}/{{relpath}}\". See the README for the  ");
+    println!("                                         list of placeholders.                                      ");
+    println!("                                                                                                    ");
+    println!("[/rename <pattern> <replace>] Optional - Rewrites file names with a regular expression. Can be      ");
+    println!("                                         given more than once.                                      ");
+    println!("                                                                                                    ");
+    println!("[/lower] [/underscores]       Optional - Lower-cases names, replaces spaces with underscores,       ");
+    println!("[/sanitize] [/maxname <len>]             replaces characters Windows does not allow and shortens    ");
+    println!("                                         names to at most <len> characters.                         ");
+    println!("                                                                                                    ");
+    println!("[/types <type,type,...>]      Optional - Only moves files whose detected content type is listed,    ");
+    println!("                                         e.g. pdf,zip,png,gzip,csv,text. See the README for more.   ");
+    println!("                                                                                                    ");
+    println!("[/review <review-directory>]  Optional - Moves files whose extension does not match their content   ");
+    println!("                                         into the review-directory and logs the mismatch.           ");
+    println!("                                                                                                    ");
+    println!("[/metrics <address:port>]     Optional - Serves Prometheus metrics at http://<address:port>/metrics.");
+    println!("                                                                                                    ");
+    println!("[/control <address:port>]     Optional - Serves GET /status and POST /pause, /resume, /sweep and    ");
+    println!("                                         /reload on a loopback address such as 127.0.0.1:<port>,    ");
+    println!("                                         or on unix:<socket-path>.                                  ");
+    println!("                                                                                                    ");
+    println!("[/before <command>]           Optional - Runs a command before each transfer, after each transfer   ");
+    println!("[/onfile <command>]                      and after each cycle that transferred or failed something. ");
+    println!("[/oncycle <command>]                     MOVER_SOURCE, MOVER_DEST, MOVER_SIZE and MOVER_HASH are    ");
+    println!("[/hooktimeout <seconds>]                 set for file hooks. A file is skipped if /before exits     ");
+    println!("                                         with a non-zero code. The default timeout is 60 seconds.   ");
+    println!("                                                                                                    ");
+    println!("[/webhook <url>]              Optional - POSTs JSON events to an http:// URL. Can be given more     ");
+    println!("[/webhookevents <e,e,...>]               than once. Events are transfer, summary, error,            ");
+    println!("[/webhookbatch <seconds>]                path-missing, path-restored and fatal (all by default).    ");
+    println!("[/webhookretries <n>]                    Events are sent in batches every 5 seconds, with 3 retries ");
+    println!("[/webhooktimeout <seconds>]              and a 5 second timeout by default.                         ");
+    println!("                                                                                                    ");
+    println!("[/config <config-file>]       Optional - Reads more options from a file. The file is read again     ");
+    println!("                                         when the configuration is reloaded.                        ");
+    println!("                                                                                                    ");
+    println!("[/disposal <policy>]          Optional - What happens to originals after a move: delete (the        ");
+    println!("[/trashdays <days>]                      default), trash:<directory> to keep them in a dated trash, ");
+    println!("                                         purged after <days>, or rename-suffix:<suffix> to rename   ");
+    println!("                                         them in place, e.g. rename-suffix:.done.                   ");
+    println!("                                                                                                    ");
+    println!("[/manifest <cycle|day>]       Optional - Writes a manifest of the delivered files (relative path,   ");
+    println!("[/manifestformat <csv|json>]             size, SHA-256 checksum and delivery time) into the         ");
+    println!("[/success]                               to-directory for each cycle or each day. /success writes a ");
+    println!("                                         _SUCCESS marker when a cycle finishes without errors.      ");
+    println!("                                                                                                    ");
+    println!("[/dirs <policy>]              Optional - What happens to emptied subdirectories of the from-        ");
+    println!("[/protect <directory>]                   directory: remove (the default), keep, or empty:<seconds>  ");
+    println!("[/removeroot]                            to remove them once empty for that long. /protect keeps a  ");
+    println!("                                         directory. /removeroot also removes the from-directory and ");
+    println!("                                         needs /lockdir.                                            ");
+    println!("                                                                                                    ");
+    println!("[/missing <policy>]           Optional - What to do when the from-directory or to-directory is      ");
+    println!("                                         missing: wait (the default), timeout:<seconds> to wait and ");
+    println!("                                         then exit with code 17, skip to skip sweeps until it is    ");
+    println!("                                         back, or create to create a missing to-directory.          ");
+    println!("                                                                                                    ");
+    println!("[/reserve <size>]             Optional - Skips files that would leave less than <size> free on the  ");
+    println!("                                         to-directory's disk. Files always need room to fit.        ");
+    println!("                                                                                                    ");
+    println!("[/quota <size>]               Optional - The most the to-directory may hold. When full, files wait  ");
+    println!("[/quotamode <pause|evict>]               (pause, the default) or the oldest files are removed to    ");
+    println!("                                         make room (evict). Sizes can end with K, M or G.           ");
+    println!("                                                                                                    ");
+    println!("[/requiremount <mode>]        Optional - Pauses transfers while the directory is not a mount point, ");
+    println!("[/fstype <type,type,...>]                or with /fstype not on one of the file system types, e.g.  ");
+    println!("                                         nfs,nfs4,cifs. <mode> is to, from or both. Stops filling an");
+    println!("                                         empty mount point. Unix only, /fstype only on Linux.       ");
+    println!("                                                                                                    ");
+    println!("[/lockdir <directory>]        Optional - Only one mover can run per from-directory. The lock file is");
+    println!("[/waitforlock]                           .mover.lock in the from-directory or a file in <directory>.");
+    println!("                                         A second mover exits with code 16, or waits for the lock   ");
+    println!("                                         with /waitforlock.                                         ");
+    println!("                                                                                                    ");
+    println!("[/label <name>]               Optional - A label for the from-directory, used by {{label}}. The       ");
+    println!("                                         default value is the name of the from-directory.           ");
+    println!("                                                                                                    ");
+    println!("Signals                       SIGINT and SIGTERM finish the current file, write the logs and exit   ");
+    println!("                              with code 15. SIGUSR1 pauses sweeps, SIGUSR2 resumes them and SIGHUP  ");
+    println!("                              reloads the configuration.                                            ");
+    println!("                                                                                                    ");
+    println!("[/q] [/v] [/vv]               Optional - Prints only errors, also debug messages, or everything.    ");
+    println!("                                                                                                    ");
+    println!("[/consolelevel <level>]       Optional - The lowest level printed to the console or written to the  ");
+    println!("[/loglevel <level>]                      log file: error, warn, info, debug or trace. The default   ");
+    println!("                                         value is info.                                             ");
+    println!("                                                                                                    ");
+    println!("[/log <log-directory>]        Optional - The directory log files are written to. The default value  ");
+    println!("                                         is C:\\mover\\logs.                                          ");
+    println!("                                                                                                    ");
+    println!("[/syslog <target>]            Optional - Also sends logs to syslog, target is unix:<socket-path> or ");
+    println!("[/sysloglevel <level>]                   udp:<host>:<port>. The default level is info.              ");
+    println!("                                                                                                    ");
+    println!("[/journald]                   Optional - Also sends logs to the systemd journal (Linux only). The   ");
+    println!("[/journaldlevel <level>]                 default level is info. The default socket is               ");
+    println!("[/journaldsocket <path>]                 /run/systemd/journal/socket.                               ");
+    println!("                                                                                                    ");
+    println!("[/logdays <days>]             Optional - Log retention. Keeps the last <days> days of logs, caps the");
+    println!("[/logmaxsize <size>]                     total size of the logs, starts a new log file once the     ");
+    println!("[/logrollsize <size>]                    current one reaches <size> and compresses logs from        ");
+    println!("[/loggzip]                               previous days. Sizes can end with K, M or G.               ");
+    println!("                                                                                                    ");
+    println!("mover.exe undo /since <time>  Reverses the transfers recorded since <time> (YYYY-MM-DD [HH:MM[:SS]])");
+    println!("mover.exe undo /session <id>  or of one session, newest first. Add /dryrun to preview, /log if logs ");
+    println!("                              are not in the default directory. Conflicts are reported and skipped. ");
+    println!("                                                                                                    ");
+    println!("mover.exe history             Lists past transfers. Filter with /file <glob>, /since <time>,        ");
+    println!("                              /until <time>, /label <label>, /status <moved|copied|failed> and      ");
+    println!("                              /dest <glob>, print with /format <table|csv|json>.                    ");
+    println!("                                                                                                    ");
+    println!("restore, undo and history are only read as commands when no directory of that name exists in the    ");
+    println!("current directory, so mover.exe history <to> still moves the files of a directory named history.    ");
     println!("----------------------------------------------------------------------------------------------------");
 }
 
+/**
+ * Exits after SIGINT or SIGTERM, once the current file is done. Logs and queued webhooks are sent
+ * first.
+ */
+pub fn shutdown(options: &mut Options) {
+    let ec = EC::Shutdown;
+    options.update_status(State::Idle, None);
+    options.logger().log_fields(
+        Level::Info,
+        get_exit_msg(&ec),
+        vec![("EC", get_exit_code(&ec).to_string())],
+    );
+    options.logger().flush_logs();
+    options.flush_webhooks();
+    process::exit(get_exit_code(&ec));
+}
+
 /**
  * Exits the program with the provided error code. On exit, this will also display the program information.
  * TODO: Handle empty string values in a better way. (Maybe a macro?)
@@ -219,7 +1512,85 @@ pub fn quit<T: Into<String>>(ec: &EC, options: &mut Options, err: T) {
     } else {
         format!("{}\n{}\n", get_exit_msg(ec), err)
     };
-    options.logger().log_and_flush(&msg);
+    let fields = vec![("EC", get_exit_code(ec).to_string())];
+    options.logger().log_fields(Level::Error, msg, fields);
+    options.logger().flush_logs();
+    options.flush_webhooks();
     print_about_info();
     process::exit(get_exit_code(ec));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * An empty directory for a test, with 'from' and 'to' directories inside it.
+     */
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("mover-test-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("from")).unwrap();
+        fs::create_dir_all(dir.join("to")).unwrap();
+        dir
+    }
+
+    fn test_options(dir: &Path, extra: &[&str]) -> Options {
+        let mut args: Vec<String> = vec![String::from("mover")];
+        for path in ["from", "to"] {
+            args.push(dir.join(path).to_str().unwrap().to_string());
+        }
+        args.push(String::from("/log"));
+        args.push(dir.join("logs").to_str().unwrap().to_string());
+        args.push(String::from("/q"));
+        args.extend(extra.iter().map(|arg| arg.to_string()));
+        Options::new(&args)
+    }
+
+    #[test]
+    fn flatten_skips_files_with_the_same_destination() {
+        let dir = test_dir("flatten");
+        for (sub, content) in [("a", "one"), ("b", "two")] {
+            fs::create_dir_all(dir.join("from").join(sub)).unwrap();
+            fs::write(dir.join("from").join(sub).join("x.txt"), content).unwrap();
+        }
+        let mut options = test_options(&dir, &["/flatten", "/c", "/o", "/versions", "2"]);
+        for _ in 0..3 {
+            move_content(&mut options).unwrap();
+        }
+
+        // Only the file found first is copied, and it is not overwritten by the other one later.
+        let records = records::find(&dir.join("logs"), None, None, |_| true).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records::text(&records[0], "action"), "copied");
+        let first = PathBuf::from(records::text(&records[0], "from"));
+        let content = fs::read_to_string(dir.join("to").join("x.txt")).unwrap();
+        assert_eq!(content, fs::read_to_string(&first).unwrap());
+        assert!(!dir.join("to").join(versions::VERSIONS_DIR).exists());
+        assert_eq!(options.collisions().len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn removeroot_removes_the_emptied_from_directory() {
+        let dir = test_dir("removeroot");
+        fs::create_dir_all(dir.join("from").join("sub")).unwrap();
+        fs::write(dir.join("from").join("sub").join("x.txt"), "x").unwrap();
+        let locks = dir.join("locks");
+        fs::create_dir_all(&locks).unwrap();
+        let mut options = test_options(
+            &dir,
+            &[
+                "/removeroot",
+                "/lockdir",
+                locks.to_str().unwrap(),
+                "/missing",
+                "skip",
+            ],
+        );
+        move_content(&mut options).unwrap();
+        assert!(dir.join("to").join("sub").join("x.txt").exists());
+        assert!(!dir.join("from").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,309 @@
+use super::records;
+use chrono::{DateTime, Local};
+use serde_json::{json, Value};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/**
+ * Written to the 'to' directory when a cycle finishes without errors. See /success.
+ */
+pub const SUCCESS_MARKER: &str = "_SUCCESS";
+
+/**
+ * The start of the name of every manifest. See /manifest.
+ */
+pub const MANIFEST_PREFIX: &str = "_manifest-";
+
+/**
+ * How many files a manifest covers. See /manifest.
+ */
+#[derive(Clone, Copy, PartialEq)]
+pub enum Period {
+    Cycle,
+    Day,
+}
+
+impl Period {
+    pub fn parse(value: &str) -> Option<Period> {
+        match value.to_lowercase().as_str() {
+            "cycle" => Some(Period::Cycle),
+            "day" => Some(Period::Day),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Period::Cycle => "cycle",
+            Period::Day => "day",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    pub fn parse(value: &str) -> Option<Format> {
+        match value.to_lowercase().as_str() {
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+        }
+    }
+}
+
+/**
+ * A file delivered to the 'to' directory, with its path relative to it.
+ */
+#[derive(Clone)]
+pub struct Entry {
+    pub path: String,
+    pub bytes: u64,
+    pub sha256: String,
+    pub delivered: DateTime<Local>,
+}
+
+/**
+ * The files delivered in the current cycle, written out as a manifest in the 'to' directory when
+ * the cycle finishes: "_manifest-<YYYYMMDD-HHMMSS>.<format>" for each cycle that delivered files,
+ * or "_manifest-<YYYY-MM-DD>.<format>" for each day, added to by every cycle that day.
+ */
+pub struct Manifest {
+    pub period: Period,
+    pub format: Format,
+    pub success: bool,
+    entries: Vec<Entry>,
+    missed: bool,
+}
+
+impl Manifest {
+    pub fn new(period: Period, format: Format, success: bool) -> Manifest {
+        Manifest {
+            period,
+            format,
+            success,
+            entries: Vec::new(),
+            missed: false,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let mut description = format!("each {} as {}", self.period.as_str(), self.format.as_str());
+        if self.success {
+            description.push_str(format!(", {} marker", SUCCESS_MARKER).as_str());
+        }
+        description
+    }
+
+    /**
+     * Adds a delivered file. The success marker is removed on the first file of a cycle, so it is
+     * only there while no batch is in progress.
+     */
+    pub fn add(&mut self, to_dir: &Path, entry: Entry) -> io::Result<()> {
+        if self.entries.is_empty() && self.success {
+            match fs::remove_file(to_dir.join(SUCCESS_MARKER)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /**
+     * Notes a delivered file that could not be added, so the cycle does not count as clean.
+     */
+    pub fn miss(&mut self) {
+        self.missed = true;
+    }
+
+    /**
+     * Writes the manifest of the files delivered this cycle, then the success marker if the cycle
+     * was 'clean'. Returns the manifests written; nothing is written for a cycle that delivered no
+     * files.
+     */
+    pub fn finish(
+        &mut self,
+        to_dir: &Path,
+        cycle_start: DateTime<Local>,
+        clean: bool,
+    ) -> io::Result<Vec<PathBuf>> {
+        let entries = std::mem::take(&mut self.entries);
+        let clean = clean && !std::mem::take(&mut self.missed);
+        if entries.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut written = Vec::new();
+        match self.period {
+            Period::Cycle => {
+                let name = format!("{}", cycle_start.format("%Y%m%d-%H%M%S"));
+                let path = self.path(to_dir, &name);
+                self.write(&path, &name, &entries)?;
+                written.push(path);
+            }
+            Period::Day => {
+                // A cycle running over midnight adds to the manifests of both days.
+                let mut days: Vec<String> = entries
+                    .iter()
+                    .map(|entry| entry.delivered.format("%F").to_string())
+                    .collect();
+                days.dedup();
+                for day in days {
+                    let path = self.path(to_dir, &day);
+                    let mut day_entries = self.read(&path)?;
+                    day_entries.extend(
+                        entries
+                            .iter()
+                            .filter(|entry| entry.delivered.format("%F").to_string() == day)
+                            .cloned(),
+                    );
+                    self.write(&path, &day, &day_entries)?;
+                    written.push(path);
+                }
+            }
+        }
+        if clean && self.success {
+            let names: Vec<String> = written
+                .iter()
+                .filter_map(|path| Some(path.file_name()?.to_string_lossy().to_string()))
+                .collect();
+            write_atomic(
+                &to_dir.join(SUCCESS_MARKER),
+                &format!("{}\n", names.join("\n")),
+            )?;
+        }
+        Ok(written)
+    }
+
+    fn path(&self, to_dir: &Path, name: &str) -> PathBuf {
+        to_dir.join(format!(
+            "{}{}.{}",
+            MANIFEST_PREFIX,
+            name,
+            self.format.as_str()
+        ))
+    }
+
+    fn write(&self, path: &Path, name: &str, entries: &[Entry]) -> io::Result<()> {
+        let contents = match self.format {
+            Format::Csv => {
+                let mut contents = String::from("path,bytes,sha256,delivered\n");
+                for entry in entries {
+                    contents.push_str(&format!(
+                        "{},{},{},{}\n",
+                        records::csv_field(&entry.path),
+                        entry.bytes,
+                        entry.sha256,
+                        entry.delivered.to_rfc3339()
+                    ));
+                }
+                contents
+            }
+            Format::Json => {
+                let files: Vec<Value> = entries
+                    .iter()
+                    .map(|entry| {
+                        json!({
+                            "path": entry.path,
+                            "bytes": entry.bytes,
+                            "sha256": entry.sha256,
+                            "delivered": entry.delivered.to_rfc3339(),
+                        })
+                    })
+                    .collect();
+                let manifest = json!({
+                    "manifest": name,
+                    "files": files,
+                });
+                format!("{:#}\n", manifest)
+            }
+        };
+        write_atomic(path, &contents)
+    }
+
+    /**
+     * The entries of a manifest written earlier, e.g. before a restart, so a day's manifest keeps
+     * every file of the day.
+     */
+    fn read(&self, path: &Path) -> io::Result<Vec<Entry>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let entry = |path: &str, bytes: Option<u64>, sha256: &str, delivered: &str| {
+            Some(Entry {
+                path: path.to_string(),
+                bytes: bytes?,
+                sha256: sha256.to_string(),
+                delivered: DateTime::parse_from_rfc3339(delivered)
+                    .ok()?
+                    .with_timezone(&Local),
+            })
+        };
+        let entries = match self.format {
+            Format::Csv => contents
+                .lines()
+                .skip(1)
+                .filter_map(|line| {
+                    // Only the path is ever quoted, the other fields never hold a comma.
+                    let mut fields = line.rsplitn(4, ',');
+                    let delivered = fields.next()?;
+                    let sha256 = fields.next()?;
+                    let bytes = fields.next()?.parse().ok();
+                    let path = fields.next()?;
+                    let path = match path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) {
+                        Some(quoted) => quoted.replace("\"\"", "\""),
+                        None => path.to_string(),
+                    };
+                    entry(&path, bytes, sha256, delivered)
+                })
+                .collect(),
+            Format::Json => {
+                let manifest: Value = serde_json::from_str(&contents).unwrap_or_default();
+                manifest["files"]
+                    .as_array()
+                    .map(|files| {
+                        files
+                            .iter()
+                            .filter_map(|file| {
+                                entry(
+                                    file["path"].as_str()?,
+                                    file["bytes"].as_u64(),
+                                    file["sha256"].as_str()?,
+                                    file["delivered"].as_str()?,
+                                )
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            }
+        };
+        Ok(entries)
+    }
+}
+
+/**
+ * Writes a file under a temporary name and renames it into place, so readers never see it half
+ * written.
+ */
+fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.tmp", name));
+    let mut file = File::create(&temp)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temp, path)
+}
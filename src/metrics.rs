@@ -0,0 +1,163 @@
+use super::http;
+use super::stats::Stats;
+use std::fmt::Write;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/**
+ * Upper bounds (in seconds) of the cycle duration histogram buckets.
+ */
+const CYCLE_BUCKETS: [f64; 10] = [0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/**
+ * Values served on the /metrics endpoint in the Prometheus text format.
+ */
+#[derive(Default)]
+pub struct Metrics {
+    files_moved: u64,
+    files_copied: u64,
+    files_failed: u64,
+    bytes: u64,
+    cycle_buckets: [u64; CYCLE_BUCKETS.len()],
+    cycle_count: u64,
+    cycle_sum: f64,
+    last_success: f64,
+    retry_queue: usize,
+    path_blocked: bool,
+}
+
+impl Metrics {
+    /**
+     * Adds a finished cycle. 'retry_queue' is the number of paths that failed and will be tried
+     * again on the next cycle.
+     */
+    pub fn record_cycle(&mut self, stats: &Stats, retry_queue: usize) {
+        self.files_moved += stats.moved;
+        self.files_copied += stats.copied;
+        self.files_failed += stats.failed;
+        self.bytes += stats.bytes;
+        let secs = stats.duration.as_secs_f64();
+        for (bucket, bound) in self.cycle_buckets.iter_mut().zip(CYCLE_BUCKETS.iter()) {
+            if secs <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.cycle_count += 1;
+        self.cycle_sum += secs;
+        if stats.failed == 0 {
+            self.last_success = unix_time();
+        }
+        self.retry_queue = retry_queue;
+    }
+
+    pub fn set_path_blocked(&mut self, blocked: bool) {
+        self.path_blocked = blocked;
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        counter(
+            &mut out,
+            "mover_files_moved_total",
+            "Files moved.",
+            self.files_moved,
+        );
+        counter(
+            &mut out,
+            "mover_files_copied_total",
+            "Files copied.",
+            self.files_copied,
+        );
+        counter(
+            &mut out,
+            "mover_files_failed_total",
+            "Files that failed to transfer.",
+            self.files_failed,
+        );
+        counter(
+            &mut out,
+            "mover_bytes_transferred_total",
+            "Bytes transferred.",
+            self.bytes,
+        );
+
+        let name = "mover_cycle_duration_seconds";
+        let _ = writeln!(out, "# HELP {} Duration of a cycle.", name);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (bucket, bound) in self.cycle_buckets.iter().zip(CYCLE_BUCKETS.iter()) {
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, bucket);
+        }
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, self.cycle_count);
+        let _ = writeln!(out, "{}_sum {}", name, self.cycle_sum);
+        let _ = writeln!(out, "{}_count {}", name, self.cycle_count);
+
+        gauge(
+            &mut out,
+            "mover_last_success_timestamp_seconds",
+            "Time of the last cycle without failures.",
+            self.last_success,
+        );
+        gauge(
+            &mut out,
+            "mover_retry_queue_depth",
+            "Paths that failed and will be retried.",
+            self.retry_queue as f64,
+        );
+        gauge(
+            &mut out,
+            "mover_path_blocked",
+            "1 while waiting for a missing 'from' or 'to' directory.",
+            if self.path_blocked { 1.0 } else { 0.0 },
+        );
+        out
+    }
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn unix_time() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+/**
+ * Starts serving GET /metrics on 'addr' from a background thread.
+ */
+pub fn serve(addr: &str, metrics: Arc<Mutex<Metrics>>) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // A client that misbehaves only loses its own response.
+            let _ = stream.set_read_timeout(Some(http::TIMEOUT));
+            let _ = stream.set_write_timeout(Some(http::TIMEOUT));
+            let _ = respond(stream, &metrics);
+        }
+    });
+    Ok(())
+}
+
+fn respond(stream: TcpStream, metrics: &Arc<Mutex<Metrics>>) -> std::io::Result<()> {
+    let request = http::read_request(&stream)?;
+    if request.method == "GET" && request.path == "/metrics" {
+        let body = metrics.lock().unwrap().render();
+        http::write_response(&stream, "200 OK", CONTENT_TYPE, &body)
+    } else {
+        http::write_response(&stream, "404 Not Found", CONTENT_TYPE, "Not Found\n")
+    }
+}
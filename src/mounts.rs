@@ -0,0 +1,103 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/**
+ * Checks that 'path' is mounted: if 'fs_types' is empty, that it is on a different device than its
+ * parent directory, otherwise that the file system it is on is one of 'fs_types'. Returns the
+ * reason when it is not.
+ */
+pub fn check(path: &Path, fs_types: &[String]) -> Result<(), String> {
+    let path = path.canonicalize().map_err(|e| e.to_string())?;
+    if fs_types.is_empty() {
+        match is_mount_point(&path) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(String::from(
+                "it is on the same file system as its parent directory",
+            )),
+            Err(e) => Err(e.to_string()),
+        }
+    } else {
+        match fs_type(&path) {
+            Ok(Some(fs_type)) if fs_types.contains(&fs_type) => Ok(()),
+            Ok(Some(fs_type)) => Err(format!(
+                "its file system is {}, not {}",
+                fs_type,
+                fs_types.join(" or ")
+            )),
+            Ok(None) => Err(String::from("its file system could not be found")),
+            Err(e) => Err(format!("unable to read the mounted file systems: {}", e)),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_mount_point(path: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::metadata(path)?;
+    let parent = fs::metadata(path.join(".."))?;
+    // The root directory is its own parent.
+    Ok(metadata.dev() != parent.dev() || metadata.ino() == parent.ino())
+}
+
+#[cfg(not(unix))]
+fn is_mount_point(_path: &Path) -> io::Result<bool> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "mount points can not be checked on this platform",
+    ))
+}
+
+/**
+ * The type of the file system 'path' is on, from the longest matching mount point in
+ * /proc/self/mountinfo.
+ */
+fn fs_type(path: &Path) -> io::Result<Option<String>> {
+    let mountinfo = fs::read_to_string("/proc/self/mountinfo")?;
+    let mut found: Option<(PathBuf, String)> = None;
+    for line in mountinfo.lines() {
+        // "<id> <parent> <major:minor> <root> <mount point> <options> [fields...] - <type> ..."
+        let fields: Vec<&str> = line.split(' ').collect();
+        let separator = match fields.iter().position(|f| *f == "-") {
+            Some(index) => index,
+            None => continue,
+        };
+        let (mount_point, fs_type) = match (fields.get(4), fields.get(separator + 1)) {
+            (Some(mount_point), Some(fs_type)) => (PathBuf::from(unescape(mount_point)), fs_type),
+            _ => continue,
+        };
+        if !path.starts_with(&mount_point) {
+            continue;
+        }
+        // Later mounts over the same point hide earlier ones.
+        let longer = match &found {
+            Some((best, _)) => mount_point.components().count() >= best.components().count(),
+            None => true,
+        };
+        if longer {
+            found = Some((mount_point, fs_type.to_string()));
+        }
+    }
+    Ok(found.map(|(_, fs_type)| fs_type))
+}
+
+/**
+ * Mount points in mountinfo escape spaces and some other characters as octal, e.g. "\040".
+ */
+fn unescape(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' {
+            if let Some(Ok(c)) = value.get(i + 1..i + 4).map(|o| u8::from_str_radix(o, 8)) {
+                out.push(c);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
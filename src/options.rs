@@ -1,35 +1,157 @@
 use super::codes::*;
-use super::quit;
-use std::path::PathBuf;
-use std::{thread, time};
+use super::compare::{Comparer, Mode};
+use super::hooks::Hooks;
+use super::lock::{self, Lock};
+use super::manifest::{self, Manifest};
+use super::metrics::{self, Metrics};
+use super::mounts;
+use super::records;
+use super::rename::RenameRules;
+use super::schedule::{Cron, Schedule, Window};
+use super::signals::Signals;
+use super::space;
+use super::stats::{format_bytes, Stats};
+use super::status::{self, State, Status};
+use super::template;
+use super::versions::{Versions, VERSIONS_DIR};
+use super::webhook::{self, ErrorSink, Webhooks};
+use super::{quit, shutdown};
+use chrono::{DateTime, Local};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::{fs, thread, time};
 
 #[path = "logger.rs"]
 mod logger;
 use logger::*;
+pub use logger::{Level, Sink, DEFAULT_DIR as DEFAULT_LOG_DIR};
 
 pub struct Options {
+    args: Vec<String>,
+    config: Option<PathBuf>,
     from_dir: PathBuf,
     to_dir: PathBuf,
     interval: usize,
+    schedule: Schedule,
+    rate: Option<u64>,
     copy: bool,
     overwrite: bool,
+    versions: usize,
+    versions_dir: Option<PathBuf>,
+    flatten: bool,
+    collisions: HashSet<PathBuf>,
+    comparer: Comparer,
+    template: Option<String>,
+    rename: RenameRules,
+    types: Vec<String>,
+    review_dir: Option<PathBuf>,
+    missing: MissingPolicy,
+    missing_paths: Vec<PathBuf>,
+    mount_from: bool,
+    mount_to: bool,
+    fs_types: Vec<String>,
+    unmounted_paths: Vec<PathBuf>,
+    mounted: Option<bool>,
+    disposal: Disposal,
+    trash_days: Option<u32>,
+    dirs: DirPolicy,
+    protect: Vec<PathBuf>,
+    remove_root: bool,
+    manifest: Option<Manifest>,
+    reserve: u64,
+    quota: Option<u64>,
+    quota_evict: bool,
+    quota_used: u64,
+    short_of_space: bool,
+    label: String,
+    hostname: String,
+    session: String,
+    stats: Stats,
+    totals: Stats,
+    metrics_addr: Option<String>,
+    metrics: Option<Arc<Mutex<Metrics>>>,
+    control_addr: Option<String>,
+    status: Option<Arc<Mutex<Status>>>,
+    paused: bool,
+    signals: Option<Signals>,
+    lock_dir: Option<PathBuf>,
+    wait_for_lock: bool,
+    lock: Option<Lock>,
+    webhook_config: webhook::Config,
+    webhooks: Option<Webhooks>,
+    hooks: Hooks,
     logger: Logger,
 }
 
 impl Default for Options {
     fn default() -> Options {
         Options {
+            args: Vec::new(),
+            config: None,
             from_dir: PathBuf::from(""),
             to_dir: PathBuf::from(""),
             interval: 1,
+            schedule: Schedule::default(),
+            rate: None,
             copy: false,
             overwrite: false,
+            versions: 0,
+            versions_dir: None,
+            flatten: false,
+            collisions: HashSet::new(),
+            comparer: Comparer::default(),
+            template: None,
+            rename: RenameRules::default(),
+            types: Vec::new(),
+            review_dir: None,
+            missing: MissingPolicy::Wait,
+            missing_paths: Vec::new(),
+            mount_from: false,
+            mount_to: false,
+            fs_types: Vec::new(),
+            unmounted_paths: Vec::new(),
+            mounted: None,
+            disposal: Disposal::Delete,
+            trash_days: None,
+            dirs: DirPolicy::Remove,
+            protect: Vec::new(),
+            remove_root: false,
+            manifest: None,
+            reserve: 0,
+            quota: None,
+            quota_evict: false,
+            quota_used: 0,
+            short_of_space: false,
+            label: String::new(),
+            hostname: template::hostname(),
+            session: format!(
+                "{}-{}",
+                Local::now().format("%Y%m%d-%H%M%S"),
+                std::process::id()
+            ),
+            stats: Stats::default(),
+            totals: Stats::default(),
+            metrics_addr: None,
+            metrics: None,
+            control_addr: None,
+            status: None,
+            paused: false,
+            signals: None,
+            lock_dir: None,
+            wait_for_lock: false,
+            lock: None,
+            webhook_config: webhook::Config::default(),
+            webhooks: None,
+            hooks: Hooks::default(),
             logger: Logger::new(),
         }
     }
 }
 
 impl Options {
+    #[allow(clippy::wrong_self_convention)]
     pub fn from_dir(&self) -> PathBuf {
         self.from_dir.clone()
     }
@@ -37,6 +159,7 @@ impl Options {
         self.to_dir.clone()
     }
 
+    #[allow(clippy::wrong_self_convention)]
     pub fn from_dir_str(&self) -> &str {
         self.from_dir.to_str().unwrap()
     }
@@ -52,6 +175,20 @@ impl Options {
         self.interval
     }
 
+    /**
+     * Cron and active window scheduling. See /cron and /window.
+     */
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    /**
+     * The most bytes per second to transfer right now: the rate of the open window, or /rate.
+     */
+    pub fn rate(&self) -> Option<u64> {
+        self.schedule.rate_at(Local::now()).or(self.rate)
+    }
+
     pub fn copy(&self) -> bool {
         self.copy
     }
@@ -60,13 +197,605 @@ impl Options {
         self.overwrite
     }
 
-    pub fn new(args: &Vec<String>) -> Options {
+    pub fn flatten(&self) -> bool {
+        self.flatten
+    }
+
+    /**
+     * Files skipped in the last cycle because another file was delivered to the same path, so each
+     * collision is only warned about once.
+     */
+    pub fn collisions(&mut self) -> &mut HashSet<PathBuf> {
+        &mut self.collisions
+    }
+
+    pub fn comparer(&mut self) -> &mut Comparer {
+        &mut self.comparer
+    }
+
+    pub fn template(&self) -> Option<&str> {
+        self.template.as_deref()
+    }
+
+    pub fn rename(&self) -> &RenameRules {
+        &self.rename
+    }
+
+    pub fn review_dir(&self) -> Option<PathBuf> {
+        self.review_dir.clone()
+    }
+
+    /**
+     * Content types are only detected when something uses them, since it means reading every file.
+     */
+    pub fn sniff(&self) -> bool {
+        !self.types.is_empty()
+            || self.review_dir.is_some()
+            || self.template().is_some_and(|t| t.contains("{type}"))
+    }
+
+    /**
+     * Returns true if a file with the given content type should be moved. See /types.
+     */
+    pub fn type_allowed(&self, content_type: Option<&str>) -> bool {
+        self.types.is_empty()
+            || self
+                .types
+                .iter()
+                .any(|t| Some(t.as_str()) == content_type.or(Some("unknown")))
+    }
+
+    /**
+     * Where old versions of overwritten files are kept and how many of them, if /versions is given.
+     */
+    pub fn versions(&self) -> Option<(Versions<'_>, usize)> {
+        if self.versions == 0 {
+            return None;
+        }
+        let versions = Versions {
+            to_dir: &self.to_dir,
+            root: self.versions_dir.as_deref(),
+        };
+        Some((versions, self.versions))
+    }
+
+    /**
+     * What happens to a file in the 'from' directory once it is moved. See /disposal.
+     */
+    pub fn disposal(&self) -> &Disposal {
+        &self.disposal
+    }
+
+    /**
+     * How many days of trash to keep. See /trashdays.
+     */
+    pub fn trash_days(&self) -> Option<u32> {
+        self.trash_days
+    }
+
+    /**
+     * Returns true for files and directories left behind by /disposal, which are never moved: the
+     * trash directory and files renamed with the suffix.
+     */
+    pub fn is_disposed(&self, path: &Path) -> bool {
+        match &self.disposal {
+            Disposal::Delete => false,
+            Disposal::Trash(dir) => {
+                path == dir
+                    || path.is_dir()
+                        && dir
+                            .canonicalize()
+                            .is_ok_and(|dir| path.canonicalize().is_ok_and(|path| path == dir))
+            }
+            Disposal::Suffix(suffix) => path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().ends_with(suffix.as_str())),
+        }
+    }
+
+    /**
+     * The manifest of files delivered to the 'to' directory, if /manifest is given.
+     */
+    pub fn manifest(&mut self) -> Option<&mut Manifest> {
+        self.manifest.as_mut()
+    }
+
+    /**
+     * What happens to subdirectories of the 'from' directory once they are emptied. See /dirs.
+     */
+    pub fn dirs(&self) -> DirPolicy {
+        self.dirs
+    }
+
+    /**
+     * Returns true for directories that are never removed. See /protect.
+     */
+    pub fn is_protected(&self, dir: &Path) -> bool {
+        self.protect.iter().any(|p| p == dir)
+    }
+
+    /**
+     * Returns true if the 'from' directory itself is removed once it is empty. See /removeroot.
+     */
+    pub fn remove_root(&self) -> bool {
+        self.remove_root
+    }
+
+    /**
+     * Bytes that must stay free on the destination after each file. See /reserve.
+     */
+    pub fn reserve(&self) -> u64 {
+        self.reserve
+    }
+
+    /**
+     * The most bytes the 'to' directory may hold, and whether the oldest files are evicted to stay
+     * under it. See /quota and /quotamode.
+     */
+    pub fn quota(&self) -> Option<(u64, bool)> {
+        self.quota.map(|quota| (quota, self.quota_evict))
+    }
+
+    /**
+     * Bytes in the 'to' directory, counted at start up and on reload and kept up to date as files
+     * are transferred, versioned and evicted.
+     */
+    pub fn quota_used(&mut self) -> &mut u64 {
+        &mut self.quota_used
+    }
+
+    /**
+     * Records whether files are being held back for lack of space. Returns true if that changed.
+     */
+    pub fn set_short_of_space(&mut self, short: bool) -> bool {
+        std::mem::replace(&mut self.short_of_space, short) != short
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    /**
+     * Identifies the transfers of this run in the transfer records, e.g. for "mover undo /session".
+     * A reload keeps the same session.
+     */
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+
+    /**
+     * Adds the time, session and label to a transfer record and appends it to the records in the
+     * log directory. A failure is logged once until writing works again.
+     */
+    pub fn record(&mut self, mut record: Value) {
+        record["time"] = Value::from(Local::now().to_rfc3339());
+        record["session"] = Value::from(self.session.as_str());
+        record["label"] = Value::from(self.label.as_str());
+        let log_dir = PathBuf::from(self.logger.dir_as_str());
+        let dir = records::dir(&log_dir);
+        match records::append(&log_dir, &record) {
+            Ok(()) => self.logger.remove_flagged_path(dir),
+            Err(e) => {
+                let msg = format!(
+                    "Attempted to write a transfer record to \"{}\". Error Message: {}",
+                    dir.to_str().unwrap(),
+                    e
+                );
+                self.logger.log_error(dir, msg);
+            }
+        }
+    }
+
+    /**
+     * Statistics of the current cycle.
+     */
+    pub fn stats(&mut self) -> &mut Stats {
+        &mut self.stats
+    }
+
+    /**
+     * Statistics of every finished cycle since start up.
+     */
+    pub fn totals(&mut self) -> &mut Stats {
+        &mut self.totals
+    }
+
+    /**
+     * The metrics served on /metrics, if enabled.
+     */
+    pub fn metrics(&self) -> Option<&Arc<Mutex<Metrics>>> {
+        self.metrics.as_ref()
+    }
+
+    fn set_path_blocked(&self, blocked: bool) {
+        if let Some(metrics) = &self.metrics {
+            metrics.lock().unwrap().set_path_blocked(blocked);
+        }
+    }
+
+    /**
+     * The status shared with the control endpoint, if enabled.
+     */
+    pub fn status(&self) -> Option<&Arc<Mutex<Status>>> {
+        self.status.as_ref()
+    }
+
+    /**
+     * Updates the state shown by the control endpoint, along with the progress of the current cycle
+     * and the last error logged.
+     */
+    pub fn update_status(&mut self, state: State, current_file: Option<(&Path, u64)>) {
+        if let Some(status) = &self.status {
+            let mut status = status.lock().unwrap();
+            status.state = state;
+            status.current_file = current_file.map(|(path, _)| path.to_str().unwrap().to_string());
+            status.current_file_bytes = current_file.map_or(0, |(_, bytes)| bytes);
+            status.cycle_files_done = self.stats.moved + self.stats.copied;
+            status.cycle_bytes_done = self.stats.bytes;
+            status.last_error = self.logger.last_error().cloned();
+        }
+    }
+
+    pub fn set_next_run(&mut self, next_run: Option<DateTime<Local>>) {
+        if let Some(status) = &self.status {
+            status.lock().unwrap().next_run = next_run;
+        }
+    }
+
+    /**
+     * Returns true while sweeps are paused, through the control endpoint or SIGUSR1 and SIGUSR2.
+     */
+    pub fn paused(&mut self) -> bool {
+        let (pause, resume) = match &self.signals {
+            Some(signals) => (signals.take_pause(), signals.take_resume()),
+            None => (false, false),
+        };
+        if pause {
+            self.set_paused(true);
+        }
+        if resume {
+            self.set_paused(false);
+        }
+        match &self.status {
+            Some(status) => status.lock().unwrap().paused,
+            None => self.paused,
+        }
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        if let Some(status) = &self.status {
+            status.lock().unwrap().paused = paused;
+        }
+    }
+
+    /**
+     * Returns true once for every request to sweep now.
+     */
+    pub fn take_sweep_request(&mut self) -> bool {
+        match &self.status {
+            Some(status) => std::mem::take(&mut status.lock().unwrap().sweep_requested),
+            None => false,
+        }
+    }
+
+    /**
+     * Returns true once for every request to reload the configuration, through the control
+     * endpoint or SIGHUP.
+     */
+    pub fn take_reload_request(&mut self) -> bool {
+        let signaled = self.signals.as_ref().is_some_and(|s| s.take_reload());
+        let requested = match &self.status {
+            Some(status) => std::mem::take(&mut status.lock().unwrap().reload_requested),
+            None => false,
+        };
+        signaled || requested
+    }
+
+    /**
+     * Returns true once SIGINT or SIGTERM has been received.
+     */
+    pub fn shutdown_requested(&self) -> bool {
+        self.signals
+            .as_ref()
+            .is_some_and(|s| s.shutdown_requested())
+    }
+
+    /**
+     * Returns true for the lock file of this job, or the default lock file of another mover using
+     * the same 'from' directory. Lock files are never moved.
+     */
+    pub fn is_lock_file(&self, path: &Path) -> bool {
+        self.lock.as_ref().is_some_and(|lock| lock.path() == path)
+            || Lock::path_for(&self.from_dir, None, &self.label) == path
+    }
+
+    /**
+     * Queues a webhook event, if any webhooks are set up. See /webhook.
+     */
+    pub fn notify(&self, event: &str, data: Value) {
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.notify(event, data);
+        }
+    }
+
+    /**
+     * Waits for queued webhook events to be sent. Only used before exiting.
+     */
+    pub fn flush_webhooks(&self) {
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.flush();
+        }
+    }
+
+    /**
+     * The commands run before and after transfers. See /before, /onfile and /oncycle.
+     */
+    pub fn hooks(&mut self) -> &mut Hooks {
+        &mut self.hooks
+    }
+
+    pub fn new(args: &[String]) -> Options {
         let mut options = Options {
+            args: args.to_vec(),
             ..Default::default()
         };
-        if args.len() <= 2 {
-            quit(&EC::SuppliedArgs, &mut options, String::new());
+        if let Err((ec, msg)) = options.parse() {
+            quit(&ec, &mut options, msg);
         }
+        // Webhooks start first, so a missing path is reported to them from the first check.
+        options.start_webhooks();
+        // Checked once every option is read, so the /missing policy is known.
+        options.validate_paths();
+        options.count_quota_used();
+        options.start_services();
+        options.print_args();
+        options.warn_suffixed_files();
+        options
+    }
+
+    /**
+     * Reads the command line and configuration file again and applies them. Running services such
+     * as /metrics and /control keep their address, as do the totals since start up, the cached
+     * checksums and the files vetoed by the before_transfer hook. Webhooks are started again with
+     * the new settings, and the lock is taken again if its path changed. An invalid configuration,
+     * or a new lock that is held by someone else, is logged and the current configuration is kept.
+     */
+    pub fn reload(&mut self) {
+        self.logger.log_and_flush("Reloading the configuration...");
+        let mut options = Options {
+            args: self.args.clone(),
+            ..Default::default()
+        };
+        if let Err((ec, msg)) = options.parse() {
+            self.reload_failed(format!("{}\n{}", get_exit_msg(&ec), msg));
+            return;
+        }
+        let lock_path = Lock::path_for(
+            &options.from_dir,
+            options.lock_dir.as_deref(),
+            &options.label,
+        );
+        if self
+            .lock
+            .as_ref()
+            .is_some_and(|lock| lock.path() == lock_path)
+        {
+            options.lock = self.lock.take();
+        } else {
+            let path_str = lock_path.to_str().unwrap();
+            match Lock::try_acquire(&lock_path) {
+                Ok(Some(lock)) => options.lock = Some(lock),
+                Ok(None) => {
+                    let holder = match lock::holder(&lock_path) {
+                        Some(pid) => format!("PID {}", pid),
+                        None => String::from("an unknown process"),
+                    };
+                    let msg = format!("The lock \"{}\" is held by {}.", path_str, holder);
+                    self.reload_failed(msg);
+                    return;
+                }
+                Err(e) => {
+                    let msg = format!("Unable to open the lock \"{}\": {}", path_str, e);
+                    self.reload_failed(msg);
+                    return;
+                }
+            }
+        }
+        options.metrics_addr = self.metrics_addr.take();
+        options.metrics = self.metrics.take();
+        options.control_addr = self.control_addr.take();
+        options.status = self.status.take();
+        options.paused = self.paused;
+        options.signals = self.signals.take();
+        options.totals = self.totals.clone();
+        options.session = std::mem::take(&mut self.session);
+        let mode = options.comparer.mode();
+        options.comparer = std::mem::take(&mut self.comparer);
+        options.comparer.set_mode(mode);
+        options.hooks.keep_vetoes(&mut self.hooks);
+        options.logger.keep_state(&mut self.logger);
+        // Kept so the restore is still logged for paths that went missing before the reload, unless
+        // the reload moved the job to other paths.
+        let paths = [options.from_dir(), options.to_dir()];
+        options.missing_paths = std::mem::take(&mut self.missing_paths);
+        options.missing_paths.retain(|path| paths.contains(path));
+        options.unmounted_paths = std::mem::take(&mut self.unmounted_paths);
+        options.unmounted_paths.retain(|path| paths.contains(path));
+        *self = options;
+        self.update_path_blocked();
+        self.count_quota_used();
+        self.start_webhooks();
+        self.sync_status_info();
+        self.print_args();
+    }
+
+    /**
+     * Counts the bytes in the 'to' directory for the /quota. This walks the whole directory, so it
+     * is only done at start up and on reload.
+     */
+    fn count_quota_used(&mut self) {
+        if self.quota.is_some() {
+            self.quota_used = space::usage(&self.to_dir);
+            self.logger.log_debug(format!(
+                "The 'to' path holds {} towards the /quota.",
+                format_bytes(self.quota_used as f64)
+            ));
+        }
+    }
+
+    fn reload_failed(&mut self, msg: String) {
+        self.logger.log(
+            Level::Error,
+            format!(
+                "Unable to reload the configuration, keeping the current one.\n{}",
+                msg
+            ),
+        );
+        self.logger.flush_logs();
+    }
+
+    /**
+     * Starts the signal handlers, takes the lock for the job and starts the optional background
+     * services: the metrics and control endpoints.
+     */
+    fn start_services(&mut self) {
+        match Signals::register() {
+            Ok(signals) => self.signals = Some(signals),
+            Err(e) => quit(
+                &EC::IOGeneric,
+                self,
+                format!("Unable to handle signals: {}", e),
+            ),
+        }
+        self.acquire_lock();
+        if let Some(addr) = self.metrics_addr.clone() {
+            let shared = Arc::new(Mutex::new(Metrics::default()));
+            match metrics::serve(&addr, Arc::clone(&shared)) {
+                Ok(()) => self.metrics = Some(shared),
+                Err(e) => quit(&EC::ServerBind, self, format!("{}: {}", addr, e)),
+            }
+        }
+        if let Some(addr) = self.control_addr.clone() {
+            let shared = Arc::new(Mutex::new(Status::default()));
+            match status::serve(&addr, Arc::clone(&shared)) {
+                Ok(()) => self.status = Some(shared),
+                Err(e) => quit(&EC::ServerBind, self, format!("{}: {}", addr, e)),
+            }
+            self.sync_status_info();
+        }
+    }
+
+    /**
+     * Takes the lock for the job. If another mover holds it, quits or, with /waitforlock, waits for
+     * it to be released.
+     */
+    fn acquire_lock(&mut self) {
+        let path = Lock::path_for(&self.from_dir, self.lock_dir.as_deref(), &self.label);
+        let path_str = path.to_str().unwrap().to_string();
+        let mut waiting = false;
+        loop {
+            match Lock::try_acquire(&path) {
+                Ok(Some(lock)) => {
+                    if waiting {
+                        self.logger
+                            .log_and_flush(format!("Acquired the lock \"{}\".", path_str));
+                    }
+                    self.lock = Some(lock);
+                    return;
+                }
+                Ok(None) => {
+                    let holder = match lock::holder(&path) {
+                        Some(pid) => format!("PID {}", pid),
+                        None => String::from("an unknown process"),
+                    };
+                    if !self.wait_for_lock {
+                        let msg = format!("The lock \"{}\" is held by {}.", path_str, holder);
+                        quit(&EC::Locked, self, msg);
+                    }
+                    if !waiting {
+                        self.logger.log_and_flush(format!(
+                            "The lock \"{}\" is held by {}. Waiting for it to be released...",
+                            path_str, holder
+                        ));
+                        waiting = true;
+                    }
+                }
+                Err(e) => {
+                    let mut msg = format!("Unable to open the lock \"{}\": {}", path_str, e);
+                    if !self.from_dir.exists() {
+                        msg.push_str("\nUse /lockdir when the 'From' path can be missing.");
+                    }
+                    quit(&EC::Locked, self, msg);
+                }
+            }
+            if self.shutdown_requested() {
+                shutdown(self);
+            }
+            thread::sleep(time::Duration::from_secs(1u64));
+        }
+    }
+
+    fn start_webhooks(&mut self) {
+        if self.webhook_config.urls.is_empty() {
+            return;
+        }
+        match Webhooks::start(self.webhook_config.clone(), &self.label) {
+            Ok(webhooks) => {
+                self.logger
+                    .add_sink(Box::new(ErrorSink::new(webhooks.clone())));
+                self.webhooks = Some(webhooks);
+            }
+            Err(e) => quit(&EC::SuppliedArgs, self, e),
+        }
+    }
+
+    fn sync_status_info(&mut self) {
+        if let Some(status) = &self.status {
+            let mut status = status.lock().unwrap();
+            status.label = self.label.clone();
+            status.from_dir = self.from_dir_str().to_string();
+            status.to_dir = self.to_dir_str().to_string();
+        }
+    }
+
+    /**
+     * Reads the options from the command line in 'args', followed by the arguments in the
+     * configuration file if one is given with /config. Returns the exit code and message for the
+     * first invalid option, so a reload can keep the current configuration instead of exiting.
+     */
+    fn parse(&mut self) -> Result<(), (EC, String)> {
+        let options = self;
+        let cmd_args = &options.args.clone();
+        if cmd_args.len() <= 2 {
+            return Err((EC::SuppliedArgs, String::new()));
+        }
+        let mut args = cmd_args.to_vec();
+        if let Some(index) = cmd_args.iter().position(|arg| arg == "/config") {
+            let config = PathBuf::from(options.arg_value(cmd_args, index)?);
+            match read_config(&config) {
+                Ok(config_args) => args.extend(config_args),
+                Err(e) => {
+                    let msg = format!("{}: {}", config.to_str().unwrap(), e);
+                    return Err((EC::ConfigRead, msg));
+                }
+            }
+            options.config = Some(config);
+        }
+        let args = &args;
+        // Sinks are created after every option is read, so their levels can be given in any order.
+        let mut syslog_target = None;
+        let mut syslog_level = Level::Info;
+        let mut journald = false;
+        let mut journald_socket = None;
+        let mut journald_level = Level::Info;
+        let mut manifest_period = None;
+        let mut manifest_format = manifest::Format::Csv;
+        let mut success = false;
         for arg_index in 2..args.len() {
             // Get directory paths. Index 1 & 2 should always be 'from' & 'to' paths.
             if arg_index == 2 {
@@ -74,69 +803,544 @@ impl Options {
                 let arg_to_dir = args[arg_index].to_string();
                 options.from_dir = PathBuf::from(arg_from_dir);
                 options.to_dir = PathBuf::from(arg_to_dir);
-                options.validate_paths();
             }
             // Get options
             match args[arg_index].as_str() {
                 // Interval option
-                "/i" => {
-                    if arg_index + 1 <= args.len() {
-                        match args[arg_index + 1].parse::<usize>() {
-                            Ok(i) => {
-                                options.validate_interval(i);
-                                options.interval = i;
-                            }
-                            Err(e) => quit(&EC::SuppliedArgs, &mut options, e.to_string()),
-                        }
+                "/i" => match options.arg_value(args, arg_index)?.parse::<usize>() {
+                    Ok(i) => {
+                        options.validate_interval(i)?;
+                        options.interval = i;
                     }
-                }
+                    Err(e) => return Err((EC::SuppliedArgs, e.to_string())),
+                },
+                // Schedule options
+                "/cron" => match Cron::parse(&options.arg_value(args, arg_index)?) {
+                    Ok(cron) => options.schedule.set_cron(cron),
+                    Err(e) => return Err((EC::SuppliedArgs, e)),
+                },
+                "/window" => match Window::parse(&options.arg_value(args, arg_index)?) {
+                    Ok(window) => options.schedule.add_window(window),
+                    Err(e) => return Err((EC::SuppliedArgs, e)),
+                },
+                "/rate" => options.rate = Some(options.size_value(args, arg_index)?),
                 // Copy option
                 "/c" => options.copy = true,
                 // Overwrite option
                 "/o" => options.overwrite = true,
+                "/versions" => match options.arg_value(args, arg_index)?.parse::<usize>() {
+                    Ok(versions) if versions > 0 => options.versions = versions,
+                    _ => return Err(invalid_value("/versions")),
+                },
+                "/versionsdir" => {
+                    options.versions_dir = Some(PathBuf::from(options.arg_value(args, arg_index)?))
+                }
+                // Flatten option
+                "/flatten" => options.flatten = true,
+                // Compare option
+                "/compare" => {
+                    let value = options.arg_value(args, arg_index)?;
+                    match Mode::parse(&value) {
+                        Some(mode) => options.comparer.set_mode(mode),
+                        None => {
+                            let msg = format!("Invalid value \"{}\" for /compare.", value);
+                            return Err((EC::SuppliedArgs, msg));
+                        }
+                    }
+                }
+                // Destination template option
+                "/template" => {
+                    let value = options.arg_value(args, arg_index)?;
+                    if let Err(e) = template::validate(&value) {
+                        return Err((EC::SuppliedArgs, e));
+                    }
+                    options.template = Some(value);
+                }
+                // Rename options
+                "/rename" => {
+                    let pattern = options.arg_value(args, arg_index)?;
+                    let replacement = match args.get(arg_index + 2) {
+                        Some(replacement) => replacement.to_string(),
+                        None => {
+                            let msg = "The option /rename requires a pattern and a replacement.";
+                            return Err((EC::SuppliedArgs, msg.into()));
+                        }
+                    };
+                    if let Err(e) = options.rename.add_substitution(&pattern, &replacement) {
+                        return Err((EC::SuppliedArgs, e));
+                    }
+                }
+                "/lower" => options.rename.set_lower(),
+                "/underscores" => options.rename.set_underscores(),
+                "/sanitize" => options.rename.set_sanitize(),
+                "/maxname" => match options.arg_value(args, arg_index)?.parse::<usize>() {
+                    Ok(max_len) if max_len > 0 => options.rename.set_max_len(max_len),
+                    _ => return Err(invalid_value("/maxname")),
+                },
+                // Content type options
+                "/types" => {
+                    options.types = options
+                        .arg_value(args, arg_index)?
+                        .split(',')
+                        .map(|t| t.trim().to_lowercase())
+                        .filter(|t| !t.is_empty())
+                        .collect();
+                }
+                "/review" => {
+                    options.review_dir = Some(PathBuf::from(options.arg_value(args, arg_index)?))
+                }
+                // Log retention options
+                "/logdays" => match options.arg_value(args, arg_index)?.parse::<u32>() {
+                    Ok(days) if days > 0 => options.logger.retention().keep_days = Some(days),
+                    _ => return Err(invalid_value("/logdays")),
+                },
+                "/logmaxsize" => {
+                    let size = options.size_value(args, arg_index)?;
+                    options.logger.retention().max_total = Some(size);
+                }
+                "/logrollsize" => {
+                    let size = options.size_value(args, arg_index)?;
+                    options.logger.retention().roll_size = Some(size);
+                }
+                "/loggzip" => options.logger.retention().gzip = true,
+                // Log output options
+                "/log" => {
+                    let dir = options.arg_value(args, arg_index)?;
+                    options.logger.set_directory(PathBuf::from(dir));
+                }
+                "/syslog" => {
+                    let target = options.arg_value(args, arg_index)?;
+                    syslog_target = Some(target);
+                }
+                "/sysloglevel" => syslog_level = options.level_value(args, arg_index)?,
+                "/journald" => journald = true,
+                "/journaldlevel" => journald_level = options.level_value(args, arg_index)?,
+                "/journaldsocket" => {
+                    journald_socket = Some(options.arg_value(args, arg_index)?);
+                }
+                // Log level options
+                "/q" => options.logger.set_console_level(Level::Error),
+                "/v" => options.logger.set_console_level(Level::Debug),
+                "/vv" => options.logger.set_console_level(Level::Trace),
+                "/consolelevel" => {
+                    let level = options.level_value(args, arg_index)?;
+                    options.logger.set_console_level(level);
+                }
+                "/loglevel" => {
+                    let level = options.level_value(args, arg_index)?;
+                    options.logger.set_file_level(level);
+                }
+                // Metrics option
+                "/metrics" => options.metrics_addr = Some(options.arg_value(args, arg_index)?),
+                // Control endpoint option
+                "/control" => {
+                    let addr = options.arg_value(args, arg_index)?;
+                    status::validate_addr(&addr).map_err(|e| (EC::SuppliedArgs, e))?;
+                    options.control_addr = Some(addr);
+                }
+                // Webhook options
+                "/webhook" => {
+                    let url = options.arg_value(args, arg_index)?;
+                    webhook::validate_url(&url).map_err(|e| (EC::SuppliedArgs, e))?;
+                    options.webhook_config.urls.push(url);
+                }
+                "/webhookevents" => {
+                    let value = options.arg_value(args, arg_index)?;
+                    let events: Vec<String> = value
+                        .split(',')
+                        .map(|e| e.trim().to_lowercase())
+                        .filter(|e| !e.is_empty())
+                        .collect();
+                    match events
+                        .iter()
+                        .find(|e| !webhook::EVENTS.contains(&e.as_str()))
+                    {
+                        Some(event) => {
+                            let msg = format!("Unknown webhook event \"{}\".", event);
+                            return Err((EC::SuppliedArgs, msg));
+                        }
+                        None => options.webhook_config.events = events,
+                    }
+                }
+                "/webhookbatch" => {
+                    let secs = options.secs_value(args, arg_index, true)?;
+                    options.webhook_config.batch = time::Duration::from_secs(secs);
+                }
+                "/webhookretries" => match options.arg_value(args, arg_index)?.parse::<u32>() {
+                    Ok(retries) => options.webhook_config.retries = retries,
+                    Err(_) => return Err(invalid_value("/webhookretries")),
+                },
+                "/webhooktimeout" => {
+                    let secs = options.secs_value(args, arg_index, false)?;
+                    options.webhook_config.timeout = time::Duration::from_secs(secs);
+                }
+                // Hook options
+                "/before" => options.hooks.before = Some(options.arg_value(args, arg_index)?),
+                "/onfile" => options.hooks.on_file = Some(options.arg_value(args, arg_index)?),
+                "/oncycle" => options.hooks.on_cycle = Some(options.arg_value(args, arg_index)?),
+                "/hooktimeout" => {
+                    let secs = options.secs_value(args, arg_index, false)?;
+                    options.hooks.timeout = time::Duration::from_secs(secs);
+                }
+                // Missing path option
+                "/missing" => {
+                    let value = options.arg_value(args, arg_index)?;
+                    match MissingPolicy::parse(&value) {
+                        Some(policy) => options.missing = policy,
+                        None => {
+                            let msg = format!("Invalid value \"{}\" for /missing.", value);
+                            return Err((EC::SuppliedArgs, msg));
+                        }
+                    }
+                }
+                // Disposal options
+                "/disposal" => {
+                    let value = options.arg_value(args, arg_index)?;
+                    match Disposal::parse(&value) {
+                        Some(disposal) => options.disposal = disposal,
+                        None => {
+                            let msg = format!("Invalid value \"{}\" for /disposal.", value);
+                            return Err((EC::SuppliedArgs, msg));
+                        }
+                    }
+                }
+                "/trashdays" => match options.arg_value(args, arg_index)?.parse::<u32>() {
+                    Ok(days) if days > 0 => options.trash_days = Some(days),
+                    _ => return Err(invalid_value("/trashdays")),
+                },
+                // Directory cleanup options
+                "/dirs" => {
+                    let value = options.arg_value(args, arg_index)?;
+                    match DirPolicy::parse(&value) {
+                        Some(policy) => options.dirs = policy,
+                        None => {
+                            let msg = format!("Invalid value \"{}\" for /dirs.", value);
+                            return Err((EC::SuppliedArgs, msg));
+                        }
+                    }
+                }
+                "/protect" => {
+                    let dir = PathBuf::from(options.arg_value(args, arg_index)?);
+                    options.protect.push(dir);
+                }
+                "/removeroot" => options.remove_root = true,
+                // Manifest options
+                "/manifest" => {
+                    let value = options.arg_value(args, arg_index)?;
+                    match manifest::Period::parse(&value) {
+                        Some(period) => manifest_period = Some(period),
+                        None => {
+                            let msg = format!("Invalid value \"{}\" for /manifest.", value);
+                            return Err((EC::SuppliedArgs, msg));
+                        }
+                    }
+                }
+                "/manifestformat" => {
+                    let value = options.arg_value(args, arg_index)?;
+                    match manifest::Format::parse(&value) {
+                        Some(format) => manifest_format = format,
+                        None => {
+                            let msg = format!("Invalid value \"{}\" for /manifestformat.", value);
+                            return Err((EC::SuppliedArgs, msg));
+                        }
+                    }
+                }
+                "/success" => success = true,
+                // Space options
+                "/reserve" => options.reserve = options.size_value(args, arg_index)?,
+                "/quota" => options.quota = Some(options.size_value(args, arg_index)?),
+                "/quotamode" => match options.arg_value(args, arg_index)?.as_str() {
+                    "pause" => options.quota_evict = false,
+                    "evict" => options.quota_evict = true,
+                    _ => return Err(invalid_value("/quotamode")),
+                },
+                // Mount options
+                "/requiremount" => {
+                    let value = options.arg_value(args, arg_index)?;
+                    let (from, to) = match value.as_str() {
+                        "to" => (false, true),
+                        "from" => (true, false),
+                        "both" => (true, true),
+                        _ => {
+                            let msg = format!("Invalid value \"{}\" for /requiremount.", value);
+                            return Err((EC::SuppliedArgs, msg));
+                        }
+                    };
+                    options.require_mount(from, to)?;
+                }
+                "/fstype" => {
+                    options.fs_types = options
+                        .arg_value(args, arg_index)?
+                        .split(',')
+                        .map(|t| t.trim().to_string())
+                        .filter(|t| !t.is_empty())
+                        .collect();
+                }
+                // Lock options
+                "/lockdir" => {
+                    options.lock_dir = Some(PathBuf::from(options.arg_value(args, arg_index)?))
+                }
+                "/waitforlock" => options.wait_for_lock = true,
+                // Source label option
+                "/label" => options.label = options.arg_value(args, arg_index)?,
                 // Unknown values
                 _ => {}
             }
         }
-        options.print_args();
-        options
+        if let Some(target) = syslog_target {
+            match Syslog::new(&target, syslog_level) {
+                Ok(syslog) => options.logger.add_sink(Box::new(syslog)),
+                Err(e) => return Err((EC::SuppliedArgs, e)),
+            }
+        }
+        if journald {
+            options.add_journald(journald_socket, journald_level)?;
+        }
+        match manifest_period {
+            Some(period) => {
+                options.manifest = Some(Manifest::new(period, manifest_format, success))
+            }
+            None if success => {
+                return Err((EC::SuppliedArgs, "/success needs /manifest.".to_string()))
+            }
+            None => {}
+        }
+        // Protected directories can be given relative to the 'from' directory.
+        options.protect = options
+            .protect
+            .iter()
+            .map(|dir| options.from_dir.join(dir))
+            .collect();
+        if !options.fs_types.is_empty() && !cfg!(target_os = "linux") {
+            let msg = "The option /fstype is not supported on this platform.";
+            return Err((EC::SuppliedArgs, msg.to_string()));
+        }
+        // /fstype alone checks the 'to' directory.
+        if !options.fs_types.is_empty() && !options.mount_from {
+            options.mount_to = true;
+        }
+        if options.from_dir() == options.to_dir() {
+            return Err((EC::PathValidation, String::new()));
+        }
+        if options.remove_root && options.lock_dir.is_none() {
+            let msg = "/removeroot needs /lockdir, the lock file in the 'from' directory keeps it from ever being empty.";
+            return Err((EC::SuppliedArgs, msg.to_string()));
+        }
+        let interval = time::Duration::from_secs(options.interval as u64);
+        if options.schedule.next_run(Local::now(), interval).is_none() {
+            let msg = "The schedule given with /cron and /window never runs.";
+            return Err((EC::SuppliedArgs, msg.into()));
+        }
+        if options.label.is_empty() {
+            options.label = match options.from_dir.file_name() {
+                Some(name) => name.to_string_lossy().to_string(),
+                None => String::from("mover"),
+            };
+        }
+        Ok(())
     }
 
-    // TODO: Make waiting for a correct path an option.
-    pub fn validate_paths(&mut self) {
-        if !self.from_dir.exists() {
-            self.logger.log_and_flush(format!(
-                "The path: \"{}\" does not exist. \nWaiting until fixed...\n",
-                self.from_dir.to_str().unwrap()
-            ));
-            let mut err = false;
-            while !self.from_dir.exists() {
-                err = true;
-                thread::sleep(time::Duration::from_secs(1u64));
+    /**
+     * Checks that the 'from' and 'to' directories exist. A missing directory is handled by the
+     * /missing policy. Returns false if the current sweep should be skipped.
+     */
+    pub fn validate_paths(&mut self) -> bool {
+        self.check_path(true) && self.check_path(false) && self.check_mounts()
+    }
+
+    /**
+     * Has the /requiremount check run again on the next call to validate_paths, which otherwise
+     * only checks the mounts once per cycle.
+     */
+    pub fn recheck_mounts(&mut self) {
+        self.mounted = None;
+    }
+
+    fn check_mounts(&mut self) -> bool {
+        if let Some(mounted) = self.mounted {
+            return mounted;
+        }
+        let mounted = self.check_mount(true) && self.check_mount(false);
+        self.mounted = Some(mounted);
+        mounted
+    }
+
+    /**
+     * Files renamed with the /disposal suffix are not tracked, any file ending with the suffix is
+     * left alone. Warns at start up about the files that already do, since a producer may have named
+     * them that way.
+     */
+    fn warn_suffixed_files(&mut self) {
+        let suffix = match &self.disposal {
+            Disposal::Suffix(suffix) => suffix.clone(),
+            _ => return,
+        };
+        let mut found = 0;
+        let mut dirs = vec![self.from_dir()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if self.is_disposed(&path) {
+                    found += 1;
+                }
             }
-            if err {
-                self.logger
-                    .log_and_flush("'From' path restored, continuing...\n");
+        }
+        if found > 0 {
+            self.logger.log(
+                Level::Warn,
+                format!(
+                    "{} file(s) in the 'from' path already end with the /disposal suffix \"{}\" and will not be moved.",
+                    found, suffix
+                ),
+            );
+            self.logger.flush_logs();
+        }
+    }
+
+    /**
+     * Checks that a directory given to /requiremount is mounted. Transfers are paused, by skipping
+     * sweeps, until it is.
+     */
+    fn check_mount(&mut self, from: bool) -> bool {
+        let (required, path, name, which) = if from {
+            (self.mount_from, self.from_dir(), "From", "from")
+        } else {
+            (self.mount_to, self.to_dir(), "To", "to")
+        };
+        if !required {
+            return true;
+        }
+        match mounts::check(&path, &self.fs_types) {
+            Ok(()) => {
+                if self.unmounted_paths.contains(&path) {
+                    self.unmounted_paths.retain(|p| *p != path);
+                    self.logger.log_and_flush(format!(
+                        "'{}' path is mounted again, resuming transfers...\n",
+                        name
+                    ));
+                    self.notify(
+                        "path-restored",
+                        json!({ "path": path.to_str().unwrap(), "which": which }),
+                    );
+                    self.update_path_blocked();
+                }
+                true
+            }
+            Err(reason) => {
+                if !self.unmounted_paths.contains(&path) {
+                    self.logger.log(
+                        Level::Warn,
+                        format!(
+                            "The path: \"{}\" is not mounted, {}. \nPausing transfers until it is...\n",
+                            path.to_str().unwrap(),
+                            reason
+                        ),
+                    );
+                    self.logger.flush_logs();
+                    self.notify(
+                        "path-missing",
+                        json!({ "path": path.to_str().unwrap(), "which": which, "reason": reason }),
+                    );
+                    self.unmounted_paths.push(path);
+                    self.update_path_blocked();
+                }
+                false
             }
         }
-        if !self.to_dir.exists() {
-            self.logger.log_and_flush(format!(
-                "The path: \"{}\" does not exist. \nWaiting until fixed...\n",
-                self.to_dir.to_str().unwrap()
-            ));
-            let mut err = false;
-            while !self.to_dir.exists() {
-                err = true;
-                thread::sleep(time::Duration::from_secs(1u64));
+    }
+
+    fn update_path_blocked(&self) {
+        self.set_path_blocked(!self.missing_paths.is_empty() || !self.unmounted_paths.is_empty());
+    }
+
+    fn check_path(&mut self, from: bool) -> bool {
+        let (path, name, which) = if from {
+            (self.from_dir(), "From", "from")
+        } else {
+            (self.to_dir(), "To", "to")
+        };
+        let path_str = path.to_str().unwrap().to_string();
+        if path.exists() {
+            // Only reached after the skip policy, waiting policies report the restore themselves.
+            if self.missing_paths.contains(&path) {
+                self.restored(&path, name, which);
             }
-            if err {
-                self.logger
-                    .log_and_flush("'To' path restored, continuing...\n");
+            return true;
+        }
+        let policy = self.missing;
+        if let (MissingPolicy::Create, false) = (policy, from) {
+            if let Err(e) = fs::create_dir_all(&path) {
+                quit(&EC::ToDirCreation, self, e.to_string());
             }
+            self.logger.log(
+                Level::Warn,
+                format!(
+                    "The path: \"{}\" does not exist. \nCreated it, continuing...\n",
+                    path_str
+                ),
+            );
+            self.logger.flush_logs();
+            self.notify(
+                "path-missing",
+                json!({ "path": path_str, "which": which, "created": true }),
+            );
+            return true;
+        }
+        if self.missing_paths.contains(&path) {
+            // Already reported by an earlier skipped sweep.
+            return false;
         }
-        if self.from_dir() == self.to_dir() {
-            quit(&EC::PathValidation, self, "");
+        let action = match policy {
+            MissingPolicy::Timeout(secs) => {
+                format!("Waiting up to {} seconds until fixed...", secs)
+            }
+            MissingPolicy::Skip => String::from("Skipping sweeps until fixed..."),
+            _ => String::from("Waiting until fixed..."),
+        };
+        self.logger.log(
+            Level::Warn,
+            format!("The path: \"{}\" does not exist. \n{}\n", path_str, action),
+        );
+        self.logger.flush_logs();
+        self.notify("path-missing", json!({ "path": path_str, "which": which }));
+        self.missing_paths.push(path.clone());
+        self.update_path_blocked();
+        if let MissingPolicy::Skip = policy {
+            return false;
         }
+        self.update_status(State::WaitingForPath, None);
+        let started = time::Instant::now();
+        while !path.exists() {
+            if self.shutdown_requested() {
+                shutdown(self);
+            }
+            if let MissingPolicy::Timeout(secs) = policy {
+                if started.elapsed().as_secs() >= secs {
+                    let msg = format!(
+                        "The path: \"{}\" was missing for more than {} seconds.",
+                        path_str, secs
+                    );
+                    quit(&EC::PathMissing, self, msg);
+                }
+            }
+            thread::sleep(time::Duration::from_secs(1u64));
+        }
+        self.restored(&path, name, which);
+        self.update_status(State::Scanning, None);
+        true
+    }
+
+    fn restored(&mut self, path: &Path, name: &str, which: &str) {
+        self.missing_paths.retain(|p| p != path);
+        self.logger
+            .log_and_flush(format!("'{}' path restored, continuing...\n", name));
+        self.notify(
+            "path-restored",
+            json!({ "path": path.to_str().unwrap(), "which": which }),
+        );
+        self.update_path_blocked();
     }
 
     fn print_args(&mut self) {
@@ -145,16 +1349,429 @@ impl Options {
         init_info.push_str(format!("From Path:    {}\n", self.from_dir.to_str().unwrap()).as_str());
         init_info.push_str(format!("To Path:      {}\n", self.to_dir.to_str().unwrap()).as_str());
         init_info.push_str(format!("Interval:     {}\n", self.interval).as_str());
-        init_info.push_str(format!("Copy:         {}\n", self.copy.to_string()).as_str());
-        init_info.push_str(format!("Overwrite:    {}\n", self.overwrite.to_string()).as_str());
+        if !self.schedule.is_empty() {
+            init_info.push_str(format!("Schedule:     {}\n", self.schedule.describe()).as_str());
+        }
+        if let Some(rate) = self.rate {
+            init_info.push_str(format!("Rate Limit:   {}/s\n", format_bytes(rate as f64)).as_str());
+        }
+        init_info.push_str(format!("Copy:         {}\n", self.copy).as_str());
+        init_info.push_str(format!("Overwrite:    {}\n", self.overwrite).as_str());
+        if self.overwrite && self.versions > 0 {
+            let dir = match &self.versions_dir {
+                Some(dir) => dir.to_str().unwrap(),
+                None => VERSIONS_DIR,
+            };
+            init_info.push_str(format!("Versions:     {} in {}\n", self.versions, dir).as_str());
+        }
+        init_info.push_str(format!("Compare:      {}\n", self.comparer.mode().as_str()).as_str());
+        init_info.push_str(format!("Flatten:      {}\n", self.flatten).as_str());
+        init_info.push_str(format!("Missing:      {}\n", self.missing.describe()).as_str());
+        if !self.copy {
+            let mut disposal = self.disposal.describe();
+            if let (Disposal::Trash(_), Some(days)) = (&self.disposal, self.trash_days) {
+                disposal.push_str(format!(" (kept {} days)", days).as_str());
+            }
+            init_info.push_str(format!("Disposal:     {}\n", disposal).as_str());
+        }
+        if let Some(manifest) = &self.manifest {
+            init_info.push_str(format!("Manifest:     {}\n", manifest.describe()).as_str());
+        }
+        init_info.push_str(format!("Dirs:         {}\n", self.dirs.describe()).as_str());
+        if !self.protect.is_empty() {
+            let protect: Vec<&str> = self.protect.iter().map(|p| p.to_str().unwrap()).collect();
+            init_info.push_str(format!("Protect:      {}\n", protect.join(", ")).as_str());
+        }
+        if self.remove_root {
+            init_info.push_str("Remove Root:  true\n");
+        }
+        if self.reserve > 0 {
+            init_info.push_str(
+                format!("Reserve:      {}\n", format_bytes(self.reserve as f64)).as_str(),
+            );
+        }
+        if let Some(quota) = self.quota {
+            let mode = if self.quota_evict { "evict" } else { "pause" };
+            init_info.push_str(
+                format!("Quota:        {} ({})\n", format_bytes(quota as f64), mode).as_str(),
+            );
+        }
+        if self.mount_from || self.mount_to {
+            let paths = match (self.mount_from, self.mount_to) {
+                (true, true) => "from and to",
+                (true, false) => "from",
+                _ => "to",
+            };
+            let check = if self.fs_types.is_empty() {
+                String::from("mount point")
+            } else {
+                self.fs_types.join(" or ")
+            };
+            init_info.push_str(format!("Mounts:       {} ({})\n", paths, check).as_str());
+        }
+        if let Some(template) = &self.template {
+            init_info.push_str(format!("Template:     {}\n", template).as_str());
+        }
+        if !self.rename.is_empty() {
+            init_info.push_str(format!("Rename:       {}\n", self.rename.describe()).as_str());
+        }
+        if !self.types.is_empty() {
+            init_info.push_str(format!("Types:        {}\n", self.types.join(",")).as_str());
+        }
+        if let Some(review_dir) = &self.review_dir {
+            init_info
+                .push_str(format!("Review:       {}\n", review_dir.to_str().unwrap()).as_str());
+        }
+        if let Some(addr) = &self.metrics_addr {
+            init_info.push_str(format!("Metrics:      http://{}/metrics\n", addr).as_str());
+        }
+        if let Some(addr) = &self.control_addr {
+            init_info.push_str(format!("Control:      {}\n", addr).as_str());
+        }
+        if let Some(lock) = &self.lock {
+            init_info
+                .push_str(format!("Lock:         {}\n", lock.path().to_str().unwrap()).as_str());
+        }
+        if !self.hooks.is_empty() {
+            init_info.push_str(format!("Hooks:        {}\n", self.hooks.describe()).as_str());
+        }
+        if !self.webhook_config.urls.is_empty() {
+            init_info
+                .push_str(format!("Webhooks:     {}\n", self.webhook_config.describe()).as_str());
+        }
+        if let Some(config) = &self.config {
+            init_info.push_str(format!("Config:       {}\n", config.to_str().unwrap()).as_str());
+        }
+        init_info.push_str(format!("Label:        {}\n", self.label).as_str());
+        init_info.push_str(format!("Session:      {}\n", self.session).as_str());
         init_info.push_str(format!("Logs:         {}\n", self.logger.dir_as_str()).as_str());
+        init_info.push_str(
+            format!(
+                "Log Levels:   console {}, file {}\n",
+                self.logger.console_level().as_str(),
+                self.logger.file_level().as_str()
+            )
+            .as_str(),
+        );
+        let sinks = self.logger.describe_sinks();
+        if !sinks.is_empty() {
+            init_info.push_str(format!("Log Sinks:    {}\n", sinks).as_str());
+        }
+        let retention = self.logger.retention().describe();
+        if !retention.is_empty() {
+            init_info.push_str(format!("Log Cleanup:  {}\n", retention).as_str());
+        }
         init_info.push_str("--Press 'Ctrl + C' to quit--------------------------------------------------------------------------");
         self.logger().log_and_flush(&init_info);
     }
 
-    fn validate_interval(&mut self, i: usize) {
-        if i < 1 || i > usize::MAX {
-            quit(&EC::IValue, self, "");
+    /**
+     * Returns the value that follows the option at 'arg_index'. Fails if the value is missing.
+     */
+    fn arg_value(&self, args: &[String], arg_index: usize) -> Result<String, (EC, String)> {
+        match args.get(arg_index + 1) {
+            Some(value) => Ok(value.to_string()),
+            None => {
+                let msg = format!("The option {} requires a value.", args[arg_index]);
+                Err((EC::SuppliedArgs, msg))
+            }
+        }
+    }
+
+    /**
+     * Returns the size in bytes that follows the option at 'arg_index'. Sizes can end with K, M or
+     * G, e.g. 500K or 2G. Fails if the value is missing or invalid.
+     */
+    fn size_value(&self, args: &[String], arg_index: usize) -> Result<u64, (EC, String)> {
+        let value = self.arg_value(args, arg_index)?;
+        match parse_size(&value) {
+            Some(size) if size > 0 => Ok(size),
+            _ => {
+                let msg = format!("Invalid size \"{}\" for {}.", value, args[arg_index]);
+                Err((EC::SuppliedArgs, msg))
+            }
+        }
+    }
+
+    /**
+     * Returns the number of seconds that follows the option at 'arg_index'. Fails if the value is
+     * missing or invalid.
+     */
+    fn secs_value(
+        &self,
+        args: &[String],
+        arg_index: usize,
+        allow_zero: bool,
+    ) -> Result<u64, (EC, String)> {
+        let value = self.arg_value(args, arg_index)?;
+        match value.parse::<u64>() {
+            Ok(secs) if secs > 0 || allow_zero => Ok(secs),
+            _ => {
+                let msg = format!(
+                    "Invalid number of seconds \"{}\" for {}.",
+                    value, args[arg_index]
+                );
+                Err((EC::SuppliedArgs, msg))
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn require_mount(&mut self, from: bool, to: bool) -> Result<(), (EC, String)> {
+        self.mount_from |= from;
+        self.mount_to |= to;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn require_mount(&mut self, _from: bool, _to: bool) -> Result<(), (EC, String)> {
+        let msg = "The option /requiremount is not supported on this platform.";
+        Err((EC::SuppliedArgs, msg.to_string()))
+    }
+
+    #[cfg(unix)]
+    fn add_journald(&mut self, socket: Option<String>, level: Level) -> Result<(), (EC, String)> {
+        let socket = socket.unwrap_or_else(|| JOURNALD_SOCKET.to_string());
+        match Journald::new(&socket, level) {
+            Ok(journald) => self.logger.add_sink(Box::new(journald)),
+            Err(e) => return Err((EC::SuppliedArgs, e)),
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn add_journald(&mut self, _socket: Option<String>, _level: Level) -> Result<(), (EC, String)> {
+        let msg = "The option /journald is not supported on this platform.";
+        Err((EC::SuppliedArgs, msg.to_string()))
+    }
+
+    /**
+     * Returns the log level that follows the option at 'arg_index'. Fails if it is missing or
+     * invalid.
+     */
+    fn level_value(&self, args: &[String], arg_index: usize) -> Result<Level, (EC, String)> {
+        let value = self.arg_value(args, arg_index)?;
+        match Level::parse(&value) {
+            Some(level) => Ok(level),
+            None => {
+                let msg = format!("Invalid log level \"{}\" for {}.", value, args[arg_index]);
+                Err((EC::SuppliedArgs, msg))
+            }
+        }
+    }
+
+    fn validate_interval(&self, i: usize) -> Result<(), (EC, String)> {
+        if i < 1 {
+            return Err((EC::IValue, String::new()));
+        }
+        Ok(())
+    }
+}
+
+/**
+ * What to do when the 'from' or 'to' directory is missing. See /missing.
+ */
+#[derive(Clone, Copy)]
+enum MissingPolicy {
+    // Wait until it is back.
+    Wait,
+    // Wait up to the given number of seconds, then exit.
+    Timeout(u64),
+    // Skip sweeps until it is back.
+    Skip,
+    // Create a missing 'to' directory. A missing 'from' directory is waited for.
+    Create,
+}
+
+impl MissingPolicy {
+    fn parse(value: &str) -> Option<MissingPolicy> {
+        match value {
+            "wait" => Some(MissingPolicy::Wait),
+            "skip" => Some(MissingPolicy::Skip),
+            "create" => Some(MissingPolicy::Create),
+            _ => match value.strip_prefix("timeout:")?.parse::<u64>() {
+                Ok(secs) if secs > 0 => Some(MissingPolicy::Timeout(secs)),
+                _ => None,
+            },
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            MissingPolicy::Wait => String::from("wait"),
+            MissingPolicy::Timeout(secs) => format!("timeout:{}", secs),
+            MissingPolicy::Skip => String::from("skip"),
+            MissingPolicy::Create => String::from("create"),
+        }
+    }
+}
+
+/**
+ * What happens to a file in the 'from' directory once it has been copied. See /disposal.
+ */
+pub enum Disposal {
+    // Delete it.
+    Delete,
+    // Move it into a trash directory, by day, keeping its relative path.
+    Trash(PathBuf),
+    // Rename it in place by adding a suffix, e.g. "file.csv.done".
+    Suffix(String),
+}
+
+impl Disposal {
+    fn parse(value: &str) -> Option<Disposal> {
+        if value == "delete" {
+            Some(Disposal::Delete)
+        } else if let Some(dir) = value.strip_prefix("trash:") {
+            Some(Disposal::Trash(PathBuf::from(dir))).filter(|_| !dir.is_empty())
+        } else if let Some(suffix) = value.strip_prefix("rename-suffix:") {
+            Some(Disposal::Suffix(suffix.to_string())).filter(|_| !suffix.is_empty())
+        } else {
+            None
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Disposal::Delete => String::from("delete"),
+            Disposal::Trash(dir) => format!("trash:{}", dir.to_str().unwrap()),
+            Disposal::Suffix(suffix) => format!("rename-suffix:{}", suffix),
+        }
+    }
+}
+
+/**
+ * What happens to subdirectories of the 'from' directory once their files are moved. See /dirs.
+ */
+#[derive(Clone, Copy)]
+pub enum DirPolicy {
+    // Remove them as soon as they are empty.
+    Remove,
+    // Never remove them.
+    Keep,
+    // Remove them once they have been empty for the given number of seconds.
+    EmptyFor(u64),
+}
+
+impl DirPolicy {
+    fn parse(value: &str) -> Option<DirPolicy> {
+        match value {
+            "remove" => Some(DirPolicy::Remove),
+            "keep" => Some(DirPolicy::Keep),
+            _ => value
+                .strip_prefix("empty:")?
+                .parse::<u64>()
+                .ok()
+                .map(DirPolicy::EmptyFor),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            DirPolicy::Remove => String::from("remove"),
+            DirPolicy::Keep => String::from("keep"),
+            DirPolicy::EmptyFor(secs) => format!("empty:{}", secs),
+        }
+    }
+}
+
+/**
+ * Parses a size in bytes with an optional K, M or G suffix (powers of 1024).
+ */
+pub fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim().to_uppercase();
+    let value = value.strip_suffix('B').unwrap_or(&value);
+    let (number, multiplier) = match value.chars().last()? {
+        'K' => (&value[..value.len() - 1], 1024),
+        'M' => (&value[..value.len() - 1], 1024 * 1024),
+        'G' => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+    number.trim().parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+/**
+ * The error for an option given a value it does not accept.
+ */
+fn invalid_value(option: &str) -> (EC, String) {
+    (EC::SuppliedArgs, format!("Invalid value for {}.", option))
+}
+
+/**
+ * Reads the arguments in a configuration file. Arguments are separated by white space and can be
+ * quoted with double quotes. Lines starting with '#' are comments.
+ */
+fn read_config(path: &Path) -> std::io::Result<Vec<String>> {
+    let mut args = Vec::new();
+    for line in fs::read_to_string(path)?.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        let mut arg = String::new();
+        let mut quoted = false;
+        let mut has_arg = false;
+        for c in line.chars() {
+            match c {
+                '"' => {
+                    quoted = !quoted;
+                    has_arg = true;
+                }
+                c if c.is_whitespace() && !quoted => {
+                    if has_arg {
+                        args.push(std::mem::take(&mut arg));
+                        has_arg = false;
+                    }
+                }
+                c => {
+                    arg.push(c);
+                    has_arg = true;
+                }
+            }
+        }
+        if has_arg {
+            args.push(arg);
+        }
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, (EC, String)> {
+        let mut options = Options {
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            ..Default::default()
+        };
+        options.parse().map(|()| options)
+    }
+
+    #[test]
+    fn removeroot_needs_lockdir() {
+        let (ec, _) = parse(&["mover", "from", "to", "/removeroot"])
+            .err()
+            .unwrap();
+        assert!(matches!(ec, EC::SuppliedArgs));
+        match parse(&["mover", "from", "to", "/removeroot", "/lockdir", "locks"]) {
+            Ok(options) => assert!(options.remove_root()),
+            Err((_, msg)) => panic!("{}", msg),
+        }
+    }
+
+    #[test]
+    fn control_must_be_local() {
+        for addr in ["0.0.0.0:9465", "192.0.2.1:9465"] {
+            let (ec, _) = parse(&["mover", "from", "to", "/control", addr])
+                .err()
+                .unwrap();
+            assert!(matches!(ec, EC::SuppliedArgs));
+        }
+        for addr in ["127.0.0.1:9465", "[::1]:9465", "unix:/tmp/mover.sock"] {
+            if let Err((_, msg)) = parse(&["mover", "from", "to", "/control", addr]) {
+                panic!("{}", msg);
+            }
         }
     }
 }
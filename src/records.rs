@@ -0,0 +1,359 @@
+use chrono::{DateTime, Local, LocalResult, NaiveDate, NaiveDateTime, TimeZone};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/**
+ * Transfer records are kept as one JSON object per line in "<log-directory>/transfers/<date>.jsonl",
+ * written as each transfer happens, so "mover undo" and "mover history" can read them back. Each
+ * day also has an index, "<date>.idx", see Entry.
+ */
+pub fn dir(log_dir: &Path) -> PathBuf {
+    log_dir.join("transfers")
+}
+
+pub fn append(log_dir: &Path, record: &Value) -> io::Result<()> {
+    let dir = dir(log_dir);
+    fs::create_dir_all(&dir)?;
+    let date = Local::now().format("%F");
+    let line = format!("{}\n", record);
+    let mut file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(dir.join(format!("{}.jsonl", date)))?;
+    // Written in one go, so the end of the file is the end of this record even when another mover
+    // shares the log directory.
+    file.write_all(line.as_bytes())?;
+    let end = file.stream_position()?;
+    let entry = Entry::of(record, end - line.len() as u64, line.len() as u64);
+    OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(dir.join(format!("{}.idx", date)))?
+        .write_all(entry.line().as_bytes())
+}
+
+/**
+ * A line of a day's index: where a record is in the day's file, and the fields that history and
+ * undo look records up by. Only the records whose entry matches are read from the day's file.
+ */
+pub struct Entry {
+    offset: u64,
+    len: u64,
+    pub action: String,
+    pub label: String,
+    pub session: String,
+    /**
+     * The file names before and after the transfer.
+     */
+    pub from_name: String,
+    pub to_name: String,
+    pub to: String,
+}
+
+impl Entry {
+    fn of(record: &Value, offset: u64, len: u64) -> Entry {
+        let name = |field| {
+            Path::new(text(record, field))
+                .file_name()
+                .map_or(String::new(), |name| name.to_string_lossy().to_string())
+        };
+        Entry {
+            offset,
+            len,
+            action: text(record, "action").to_string(),
+            label: text(record, "label").to_string(),
+            session: text(record, "session").to_string(),
+            from_name: name("from"),
+            to_name: name("to"),
+            to: text(record, "to").to_string(),
+        }
+    }
+
+    /**
+     * The fields separated by tabs, with tabs, new lines and backslashes in them escaped.
+     */
+    fn line(&self) -> String {
+        let fields = [
+            self.offset.to_string(),
+            self.len.to_string(),
+            escape(&self.action),
+            escape(&self.label),
+            escape(&self.session),
+            escape(&self.from_name),
+            escape(&self.to_name),
+            escape(&self.to),
+        ];
+        format!("{}\n", fields.join("\t"))
+    }
+
+    fn parse(line: &str) -> Option<Entry> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 8 {
+            return None;
+        }
+        Some(Entry {
+            offset: fields[0].parse().ok()?,
+            len: fields[1].parse().ok()?,
+            action: unescape(fields[2]),
+            label: unescape(fields[3]),
+            session: unescape(fields[4]),
+            from_name: unescape(fields[5]),
+            to_name: unescape(fields[6]),
+            to: unescape(fields[7]),
+        })
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/**
+ * Every record written between the dates 'since' and 'until', both included and either one open,
+ * whose index entry 'matches', oldest first. Only the files of those days are read, and only the
+ * records that match. Records missing from the index, e.g. because mover stopped between writing
+ * a record and its entry, are read in full and matched the same way. Lines that can not be read,
+ * e.g. one cut short by a crash, are left out.
+ */
+pub fn find<F: Fn(&Entry) -> bool>(
+    log_dir: &Path,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    matches: F,
+) -> io::Result<Vec<Value>> {
+    let mut days = Vec::new();
+    for entry in fs::read_dir(dir(log_dir))? {
+        let path = entry?.path();
+        let date = path
+            .file_name()
+            .and_then(|name| name.to_str()?.strip_suffix(".jsonl"))
+            .and_then(|date| NaiveDate::parse_from_str(date, "%F").ok());
+        match date {
+            Some(date)
+                if since.is_none_or(|since| date >= since)
+                    && until.is_none_or(|until| date <= until) =>
+            {
+                days.push((date, path))
+            }
+            _ => continue,
+        }
+    }
+    days.sort();
+    let mut records = Vec::new();
+    for (_, path) in days {
+        records.extend(find_in_day(&path, &matches)?);
+    }
+    Ok(records)
+}
+
+fn find_in_day<F: Fn(&Entry) -> bool>(path: &Path, matches: &F) -> io::Result<Vec<Value>> {
+    let mut entries = BTreeMap::new();
+    match fs::read_to_string(path.with_extension("idx")) {
+        Ok(index) => {
+            for entry in index.lines().filter_map(Entry::parse) {
+                entries.insert(entry.offset, entry);
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    // The parts of the file that no entry covers.
+    let mut gaps = Vec::new();
+    let mut indexed = 0;
+    for entry in entries.values() {
+        if entry.offset > indexed {
+            gaps.push((indexed, entry.offset));
+        }
+        indexed = indexed.max(entry.offset + entry.len);
+    }
+    if size > indexed {
+        gaps.push((indexed, size));
+    }
+    let mut found = BTreeMap::new();
+    for entry in entries.values().filter(|entry| matches(entry)) {
+        if let Some(record) = read_at(&mut file, entry.offset, entry.len)?
+            .and_then(|line| serde_json::from_str::<Value>(&line).ok())
+        {
+            found.insert(entry.offset, record);
+        }
+    }
+    for (start, end) in gaps {
+        let text = read_at(&mut file, start, end - start)?.unwrap_or_default();
+        let mut offset = start;
+        for line in text.split_inclusive('\n') {
+            if let Ok(record) = serde_json::from_str::<Value>(line) {
+                if matches(&Entry::of(&record, offset, line.len() as u64)) {
+                    found.insert(offset, record);
+                }
+            }
+            offset += line.len() as u64;
+        }
+    }
+    Ok(found.into_values().collect())
+}
+
+/**
+ * The text of 'len' bytes at 'offset', or None if it is not there or not text.
+ */
+fn read_at(file: &mut File, offset: u64, len: u64) -> io::Result<Option<String>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buf = Vec::new();
+    Read::by_ref(file).take(len).read_to_end(&mut buf)?;
+    if buf.len() as u64 != len {
+        return Ok(None);
+    }
+    Ok(String::from_utf8(buf).ok())
+}
+
+/**
+ * The text of a field of a record, or "" if it has none.
+ */
+pub fn text<'a>(record: &'a Value, field: &str) -> &'a str {
+    record[field].as_str().unwrap_or("")
+}
+
+/**
+ * The time a record was written.
+ */
+pub fn time(record: &Value) -> Option<DateTime<Local>> {
+    let time = DateTime::parse_from_rfc3339(text(record, "time")).ok()?;
+    Some(time.with_timezone(&Local))
+}
+
+/**
+ * The path of a file for a record, made absolute so it can be used from any directory.
+ */
+pub fn path_value(path: &Path) -> Value {
+    let path = std::path::absolute(path).unwrap_or(path.to_path_buf());
+    Value::from(path.to_string_lossy())
+}
+
+/**
+ * A field of a CSV line, quoted when it holds a comma, a quote or a line break.
+ */
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/**
+ * Reads a local time given on the command line: "YYYY-MM-DD", optionally followed by "HH:MM" or
+ * "HH:MM:SS" after a space or a "T", or an RFC 3339 time.
+ */
+pub fn parse_time(value: &str) -> Option<DateTime<Local>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Local));
+    }
+    let value = value.replacen('T', " ", 1);
+    let time = ["%F %T", "%F %R"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(&value, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(&value, "%F")
+                .ok()?
+                .and_hms_opt(0, 0, 0)
+        })?;
+    match Local.from_local_datetime(&time) {
+        LocalResult::Single(time) => Some(time),
+        LocalResult::Ambiguous(a, b) => Some(a.min(b)),
+        LocalResult::None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("mover-records-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn day_file(log_dir: &Path, extension: &str) -> PathBuf {
+        dir(log_dir).join(format!("{}.{}", Local::now().format("%F"), extension))
+    }
+
+    #[test]
+    fn finds_records_through_the_index() {
+        let log_dir = test_dir("index");
+        let records = [
+            json!({ "action": "moved", "label": "a", "from": "/in/x.txt", "to": "/out/x.txt" }),
+            json!({ "action": "copied", "label": "b", "from": "/in/y\ttab.txt", "to": "/out/y.txt" }),
+            json!({ "action": "moved", "label": "a", "from": "/in/z.txt", "to": "/out/Z.TXT" }),
+        ];
+        for record in &records {
+            append(&log_dir, record).unwrap();
+        }
+        let index = fs::read_to_string(day_file(&log_dir, "idx")).unwrap();
+        assert_eq!(index.lines().count(), 3);
+
+        let found = find(&log_dir, None, None, |entry| entry.label == "a").unwrap();
+        assert_eq!(found, vec![records[0].clone(), records[2].clone()]);
+        let found = find(&log_dir, None, None, |entry| {
+            entry.from_name == "y\ttab.txt"
+        })
+        .unwrap();
+        assert_eq!(found, vec![records[1].clone()]);
+        let found = find(&log_dir, None, None, |entry| entry.to_name == "Z.TXT").unwrap();
+        assert_eq!(found, vec![records[2].clone()]);
+        let _ = fs::remove_dir_all(&log_dir);
+    }
+
+    #[test]
+    fn reads_records_missing_from_the_index() {
+        let log_dir = test_dir("gaps");
+        let first = json!({ "action": "moved", "label": "a", "to": "/out/1" });
+        let second = json!({ "action": "moved", "label": "a", "to": "/out/2" });
+        append(&log_dir, &first).unwrap();
+        append(&log_dir, &second).unwrap();
+        // Lose the entry of the first record, and add a record without an entry at the end.
+        let index = fs::read_to_string(day_file(&log_dir, "idx")).unwrap();
+        fs::write(day_file(&log_dir, "idx"), index.lines().nth(1).unwrap()).unwrap();
+        let third = json!({ "action": "failed", "label": "a", "to": "/out/3" });
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(day_file(&log_dir, "jsonl"))
+            .unwrap();
+        writeln!(file, "{}", third).unwrap();
+
+        let found = find(&log_dir, None, None, |_| true).unwrap();
+        assert_eq!(found, vec![first, second, third.clone()]);
+        let found = find(&log_dir, None, None, |entry| entry.action == "failed").unwrap();
+        assert_eq!(found, vec![third]);
+        let _ = fs::remove_dir_all(&log_dir);
+    }
+}
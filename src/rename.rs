@@ -0,0 +1,139 @@
+use regex::Regex;
+
+/**
+ * Characters that Windows and SMB shares do not allow in file names.
+ */
+const ILLEGAL_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/**
+ * Rules for rewriting file names before they are copied. Regex substitutions run first, in the
+ * order they were given, followed by lower-casing, space replacement, sanitization and truncation.
+ */
+#[derive(Default)]
+pub struct RenameRules {
+    substitutions: Vec<(Regex, String)>,
+    lower: bool,
+    underscores: bool,
+    sanitize: bool,
+    max_len: Option<usize>,
+}
+
+impl RenameRules {
+    pub fn add_substitution(&mut self, pattern: &str, replacement: &str) -> Result<(), String> {
+        match Regex::new(pattern) {
+            Ok(regex) => {
+                self.substitutions.push((regex, replacement.to_string()));
+                Ok(())
+            }
+            Err(e) => Err(format!("Invalid rename pattern \"{}\". {}", pattern, e)),
+        }
+    }
+
+    pub fn set_lower(&mut self) {
+        self.lower = true;
+    }
+
+    pub fn set_underscores(&mut self) {
+        self.underscores = true;
+    }
+
+    pub fn set_sanitize(&mut self) {
+        self.sanitize = true;
+    }
+
+    pub fn set_max_len(&mut self, max_len: usize) {
+        self.max_len = Some(max_len);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.substitutions.is_empty()
+            && !self.lower
+            && !self.underscores
+            && !self.sanitize
+            && self.max_len.is_none()
+    }
+
+    /**
+     * Returns a short description of the active rules for the start up info.
+     */
+    pub fn describe(&self) -> String {
+        let mut rules = Vec::new();
+        for (regex, replacement) in &self.substitutions {
+            rules.push(format!("\"{}\" -> \"{}\"", regex.as_str(), replacement));
+        }
+        if self.lower {
+            rules.push(String::from("lower"));
+        }
+        if self.underscores {
+            rules.push(String::from("underscores"));
+        }
+        if self.sanitize {
+            rules.push(String::from("sanitize"));
+        }
+        if let Some(max_len) = self.max_len {
+            rules.push(format!("max length {}", max_len));
+        }
+        rules.join(", ")
+    }
+
+    /**
+     * Applies every rule to a file name. Path separators are always replaced with '_', and a
+     * result of "", "." or ".." falls back to the original name.
+     */
+    pub fn apply(&self, name: &str) -> String {
+        let mut new_name = name.to_string();
+        for (regex, replacement) in &self.substitutions {
+            new_name = regex
+                .replace_all(&new_name, replacement.as_str())
+                .to_string();
+        }
+        if self.lower {
+            new_name = new_name.to_lowercase();
+        }
+        if self.underscores {
+            new_name = new_name.replace(' ', "_");
+        }
+        if self.sanitize {
+            new_name = new_name
+                .chars()
+                .map(|c| {
+                    if ILLEGAL_CHARS.contains(&c) || c.is_control() {
+                        '_'
+                    } else {
+                        c
+                    }
+                })
+                .collect();
+            // Windows drops trailing dots and spaces from names.
+            new_name = new_name.trim_end_matches(['.', ' ']).to_string();
+        }
+        // A name must never turn into a path, with or without /sanitize.
+        new_name = new_name.replace(['/', '\\'], "_");
+        if let Some(max_len) = self.max_len {
+            new_name = truncate(&new_name, max_len);
+        }
+        if new_name.is_empty() || new_name == "." || new_name == ".." {
+            name.to_string()
+        } else {
+            new_name
+        }
+    }
+}
+
+/**
+ * Shortens a name to 'max_len' characters, keeping the extension when there is room for it.
+ */
+fn truncate(name: &str, max_len: usize) -> String {
+    if name.chars().count() <= max_len {
+        return name.to_string();
+    }
+    if let Some(dot) = name.rfind('.') {
+        let ext = &name[dot..];
+        let ext_len = ext.chars().count();
+        if dot > 0 && ext_len < max_len {
+            let stem: String = name[..dot].chars().take(max_len - ext_len).collect();
+            return format!("{}{}", stem, ext);
+        }
+    }
+    name.chars().take(max_len).collect()
+}
@@ -0,0 +1,232 @@
+use super::Level;
+use chrono::{Duration, NaiveDate};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/**
+ * Settings for rolling over and cleaning up the daily log files.
+ */
+#[derive(Default)]
+pub struct Retention {
+    pub keep_days: Option<u32>,
+    pub max_total: Option<u64>,
+    pub gzip: bool,
+    pub roll_size: Option<u64>,
+}
+
+/**
+ * A log file named "YYYY-MM-DD.txt", "YYYY-MM-DD.<index>.txt" or either of those with ".gz".
+ */
+struct LogFile {
+    path: PathBuf,
+    date: NaiveDate,
+    index: u32,
+    gzipped: bool,
+    len: u64,
+}
+
+impl Retention {
+    pub fn is_empty(&self) -> bool {
+        self.keep_days.is_none()
+            && self.max_total.is_none()
+            && !self.gzip
+            && self.roll_size.is_none()
+    }
+
+    /**
+     * Returns a short description of the settings for the start up info.
+     */
+    pub fn describe(&self) -> String {
+        let mut settings = Vec::new();
+        if let Some(keep_days) = self.keep_days {
+            settings.push(format!("keep {} days", keep_days));
+        }
+        if let Some(max_total) = self.max_total {
+            settings.push(format!("max {} bytes", max_total));
+        }
+        if let Some(roll_size) = self.roll_size {
+            settings.push(format!("roll over at {} bytes", roll_size));
+        }
+        if self.gzip {
+            settings.push(String::from("gzip"));
+        }
+        settings.join(", ")
+    }
+
+    /**
+     * Returns the log file to write to for the given date. When a roll over size is set, full files
+     * are skipped and "YYYY-MM-DD.1.txt", "YYYY-MM-DD.2.txt", ... are used instead.
+     */
+    pub fn log_path(&self, directory: &Path, date: &str) -> PathBuf {
+        let mut path = directory.join(format!("{}.txt", date));
+        if let Some(roll_size) = self.roll_size {
+            let mut index = 0;
+            while full(&path, roll_size) || path_gz(&path).exists() {
+                index += 1;
+                path = directory.join(format!("{}.{}.txt", date, index));
+            }
+        }
+        path
+    }
+
+    /**
+     * Compresses, then removes old log files according to the settings. The file currently being
+     * written to is never touched. Returns a message for each action taken, at the info
+     * level, or failed, at the warn level.
+     */
+    pub fn clean(
+        &self,
+        directory: &Path,
+        today: NaiveDate,
+        current: &Path,
+    ) -> Vec<(Level, String)> {
+        let mut messages = Vec::new();
+        if self.is_empty() {
+            return messages;
+        }
+        let mut files = match list_logs(directory) {
+            Ok(files) => files,
+            Err(e) => {
+                let msg = format!(
+                    "Attempted to list the log directory \"{}\". Error Message: {}",
+                    directory.to_str().unwrap(),
+                    e
+                );
+                messages.push((Level::Warn, msg));
+                return messages;
+            }
+        };
+        files.retain(|f| f.path != current);
+        files.sort_by_key(|f| (f.date, f.index));
+
+        if self.gzip {
+            for file in files.iter_mut().filter(|f| !f.gzipped && f.date < today) {
+                match gzip(&file.path) {
+                    Ok(gz_path) => {
+                        let msg = format!(
+                            "Compressed the log file \"{}\".",
+                            file.path.to_str().unwrap()
+                        );
+                        messages.push((Level::Info, msg));
+                        file.len = fs::metadata(&gz_path).map(|m| m.len()).unwrap_or(0);
+                        file.path = gz_path;
+                        file.gzipped = true;
+                    }
+                    Err(e) => {
+                        let msg = format!(
+                            "Attempted to compress the log file \"{}\". Error Message: {}",
+                            file.path.to_str().unwrap(),
+                            e
+                        );
+                        messages.push((Level::Warn, msg));
+                    }
+                }
+            }
+        }
+
+        let mut remove = Vec::new();
+        if let Some(keep_days) = self.keep_days {
+            let oldest = today - Duration::days(i64::from(keep_days.max(1)) - 1);
+            remove.extend(
+                files
+                    .iter()
+                    .filter(|f| f.date < oldest)
+                    .map(|f| f.path.clone()),
+            );
+        }
+        if let Some(max_total) = self.max_total {
+            // Remove the oldest files first until everything left fits.
+            let kept: Vec<&LogFile> = files.iter().filter(|f| !remove.contains(&f.path)).collect();
+            let current_len = fs::metadata(current).map(|m| m.len()).unwrap_or(0);
+            let mut total: u64 = current_len + kept.iter().map(|f| f.len).sum::<u64>();
+            for file in kept {
+                if total <= max_total {
+                    break;
+                }
+                total -= file.len;
+                remove.push(file.path.clone());
+            }
+        }
+        for path in remove {
+            let message = match fs::remove_file(&path) {
+                Ok(()) => (
+                    Level::Info,
+                    format!("Removed the old log file \"{}\".", path.to_str().unwrap()),
+                ),
+                Err(e) => (
+                    Level::Warn,
+                    format!(
+                        "Attempted to remove the old log file \"{}\". Error Message: {}",
+                        path.to_str().unwrap(),
+                        e
+                    ),
+                ),
+            };
+            messages.push(message);
+        }
+        messages
+    }
+}
+
+fn full(path: &Path, roll_size: u64) -> bool {
+    match fs::metadata(path) {
+        Ok(meta) => meta.len() >= roll_size,
+        Err(_) => false,
+    }
+}
+
+fn path_gz(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap().to_os_string();
+    name.push(".gz");
+    path.with_file_name(name)
+}
+
+fn list_logs(directory: &Path) -> io::Result<Vec<LogFile>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if let Some(mut file) = parse_name(&name) {
+            file.path = entry.path();
+            file.len = entry.metadata()?.len();
+            files.push(file);
+        }
+    }
+    Ok(files)
+}
+
+fn parse_name(name: &str) -> Option<LogFile> {
+    let (name, gzipped) = match name.strip_suffix(".gz") {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+    let name = name.strip_suffix(".txt")?;
+    let date = NaiveDate::parse_from_str(name.get(..10)?, "%F").ok()?;
+    let index = match &name[10..] {
+        "" => 0,
+        rest => rest.strip_prefix('.')?.parse::<u32>().ok()?,
+    };
+    Some(LogFile {
+        path: PathBuf::new(),
+        date,
+        index,
+        gzipped,
+        len: 0,
+    })
+}
+
+/**
+ * Compresses a file to "<name>.gz" and removes the original.
+ */
+fn gzip(path: &Path) -> io::Result<PathBuf> {
+    let gz_path = path_gz(path);
+    let mut input = File::open(path)?;
+    let mut encoder = GzEncoder::new(File::create(&gz_path)?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    fs::remove_file(path)?;
+    Ok(gz_path)
+}
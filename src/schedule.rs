@@ -0,0 +1,423 @@
+use super::options::parse_size;
+use super::stats::format_bytes;
+use chrono::{
+    DateTime, Datelike, Duration, Local, LocalResult, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeZone, Timelike,
+};
+
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const WEEKDAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/**
+ * How far ahead to look for the next match before deciding an expression never matches.
+ */
+const SEARCH_DAYS: i64 = 5 * 366;
+
+/**
+ * A five field cron expression: minute, hour, day of month, month and day of week. Fields accept
+ * '*', numbers, names, ranges, lists and steps (e.g. "8-18", "MON-FRI", "1,15", "0-30/10"). As
+ * in cron, when both the day of month and the day of week are restricted, a day matching either
+ * one runs.
+ */
+pub struct Cron {
+    expr: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    pub fn parse(expr: &str) -> Result<Cron, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!(
+                "The cron expression \"{}\" must have 5 fields: minute hour day month weekday.",
+                expr
+            ));
+        }
+        let invalid = |e: String| format!("Invalid cron expression \"{}\": {}", expr, e);
+        let mut weekdays = parse_field(fields[4], 0, 7, &WEEKDAYS).map_err(invalid)?;
+        // Both 0 and 7 are Sunday.
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Cron {
+            expr: expr.to_string(),
+            minutes: parse_field(fields[0], 0, 59, &[]).map_err(invalid)?,
+            hours: parse_field(fields[1], 0, 23, &[]).map_err(invalid)?,
+            days: parse_field(fields[2], 1, 31, &[]).map_err(invalid)?,
+            months: parse_field(fields[3], 1, 12, &MONTHS).map_err(invalid)?,
+            weekdays,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let day = bit(&self.days, date.day());
+        let weekday = bit(&self.weekdays, date.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (false, true) => day,
+            (true, false) => weekday,
+            (false, false) => day || weekday,
+        }
+    }
+
+    /**
+     * The first time after 'after' that matches, in local time. Times skipped by a daylight saving
+     * change do not run, and times repeated by one only run the first time.
+     */
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = start + Duration::days(SEARCH_DAYS);
+        let mut t = start;
+        while t < limit {
+            if !bit(&self.months, t.month()) {
+                let (year, month) = if t.month() == 12 {
+                    (t.year() + 1, 1)
+                } else {
+                    (t.year(), t.month() + 1)
+                };
+                t = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.day_matches(t.date()) {
+                t = t.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if !bit(&self.hours, t.hour()) {
+                t = t.date().and_hms_opt(t.hour(), 0, 0)? + Duration::hours(1);
+            } else if !bit(&self.minutes, t.minute()) {
+                t += Duration::minutes(1);
+            } else {
+                match Local.from_local_datetime(&t) {
+                    LocalResult::Single(time) => return Some(time),
+                    LocalResult::Ambiguous(a, b) if a.min(b) > after => return Some(a.min(b)),
+                    // Skipped, or already passed the first time.
+                    _ => t += Duration::minutes(1),
+                }
+            }
+        }
+        None
+    }
+}
+
+fn bit(mask: &u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+/**
+ * Parses one cron field into a bit mask of the values it allows.
+ */
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value = |s: &str| -> Result<u32, String> {
+        let upper = s.to_uppercase();
+        let value = match names.iter().position(|n| *n == upper) {
+            Some(index) => index as u32 + if min == 1 { 1 } else { 0 },
+            None => s
+                .parse::<u32>()
+                .map_err(|_| format!("\"{}\" is not a valid value.", s))?,
+        };
+        if value < min || value > max {
+            return Err(format!("{} is not between {} and {}.", value, min, max));
+        }
+        Ok(value)
+    };
+    let mut mask = 0;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("\"{}\" is not a valid step.", step)),
+            },
+            None => (item, 1),
+        };
+        let (first, last) = if range == "*" {
+            (min, max)
+        } else if let Some((first, last)) = range.split_once('-') {
+            (value(first)?, value(last)?)
+        } else if item.contains('/') {
+            // "5/10" means from 5 to the end in steps of 10.
+            (value(range)?, max)
+        } else {
+            let single = value(range)?;
+            (single, single)
+        };
+        if first > last {
+            return Err(format!("The range \"{}\" is backwards.", range));
+        }
+        for v in (first..=last).step_by(step as usize) {
+            mask |= 1 << v;
+        }
+    }
+    Ok(mask)
+}
+
+/**
+ * A time of day during which sweeps may run, optionally with its own interval and rate limit.
+ * Windows that end before they start run over midnight, e.g. 22:00-06:00.
+ */
+pub struct Window {
+    start: NaiveTime,
+    end: NaiveTime,
+    interval: Option<std::time::Duration>,
+    rate: Option<u64>,
+}
+
+impl Window {
+    /**
+     * Parses "HH:MM-HH:MM", optionally followed by "=" and a comma separated list of the interval
+     * in seconds and the rate limit in bytes per second, e.g. "22:00-06:00=5,10M/s".
+     */
+    pub fn parse(value: &str) -> Result<Window, String> {
+        let invalid = || {
+            format!(
+                "Invalid window \"{}\". Expected HH:MM-HH:MM, optionally followed by =<seconds>, =<size>/s or =<seconds>,<size>/s.",
+                value
+            )
+        };
+        let (times, settings) = value.split_once('=').unwrap_or((value, ""));
+        let mut interval = None;
+        let mut rate = None;
+        if value.contains('=') {
+            for setting in settings.split(',') {
+                if let Some(size) = setting.strip_suffix("/s") {
+                    match parse_size(size) {
+                        Some(size) if size > 0 && rate.is_none() => rate = Some(size),
+                        _ => return Err(invalid()),
+                    }
+                } else {
+                    match setting.parse::<u64>() {
+                        Ok(secs) if secs > 0 && interval.is_none() => {
+                            interval = Some(std::time::Duration::from_secs(secs))
+                        }
+                        _ => return Err(invalid()),
+                    }
+                }
+            }
+        }
+        let (start, end) = times.split_once('-').ok_or_else(invalid)?;
+        let time = |s: &str| NaiveTime::parse_from_str(s.trim(), "%H:%M").map_err(|_| invalid());
+        let (start, end) = (time(start)?, time(end)?);
+        if start == end {
+            return Err(format!(
+                "The window \"{}\" starts and ends at the same time.",
+                value
+            ));
+        }
+        Ok(Window {
+            start,
+            end,
+            interval,
+            rate,
+        })
+    }
+
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /**
+     * The next time this window opens after 'after'.
+     */
+    fn next_start(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let mut date = after.date_naive();
+        for _ in 0..3 {
+            if let Some(start) = local_time(date.and_time(self.start)) {
+                if start > after {
+                    return Some(start);
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    fn describe(&self) -> String {
+        let mut description = format!(
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        );
+        if let Some(interval) = self.interval {
+            description.push_str(&format!(" every {}s", interval.as_secs()));
+        }
+        if let Some(rate) = self.rate {
+            description.push_str(&format!(" at {}/s", format_bytes(rate as f64)));
+        }
+        description
+    }
+}
+
+/**
+ * Converts a local date and time. A time skipped by a daylight saving change becomes the first
+ * minute after the change.
+ */
+fn local_time(time: NaiveDateTime) -> Option<DateTime<Local>> {
+    let mut time = time;
+    for _ in 0..=180 {
+        match Local.from_local_datetime(&time) {
+            LocalResult::Single(t) => return Some(t),
+            LocalResult::Ambiguous(a, b) => return Some(a.min(b)),
+            LocalResult::None => time += Duration::minutes(1),
+        }
+    }
+    None
+}
+
+/**
+ * When sweeps run: every interval (see /i), at the times of a cron expression, and either one
+ * limited to active windows.
+ */
+#[derive(Default)]
+pub struct Schedule {
+    cron: Option<Cron>,
+    windows: Vec<Window>,
+}
+
+impl Schedule {
+    pub fn set_cron(&mut self, cron: Cron) {
+        self.cron = Some(cron);
+    }
+
+    pub fn add_window(&mut self, window: Window) {
+        self.windows.push(window);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cron.is_none() && self.windows.is_empty()
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(cron) = &self.cron {
+            parts.push(format!("cron \"{}\"", cron.expr));
+        }
+        if !self.windows.is_empty() {
+            let windows: Vec<String> = self.windows.iter().map(|w| w.describe()).collect();
+            parts.push(format!("windows {}", windows.join(", ")));
+        }
+        parts.join(", ")
+    }
+
+    fn window_at(&self, time: DateTime<Local>) -> Option<&Window> {
+        let time = time.naive_local().time();
+        self.windows.iter().find(|w| w.contains(time))
+    }
+
+    /**
+     * The rate limit of the window open at 'time', if it has one.
+     */
+    pub fn rate_at(&self, time: DateTime<Local>) -> Option<u64> {
+        self.window_at(time)?.rate
+    }
+
+    fn in_window(&self, time: DateTime<Local>) -> bool {
+        self.windows.is_empty() || self.window_at(time).is_some()
+    }
+
+    fn next_window_start(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        self.windows
+            .iter()
+            .filter_map(|w| w.next_start(after))
+            .min()
+    }
+
+    /**
+     * The time of the next sweep after 'now', or None if the schedule never runs. The flag is true
+     * when the time comes from the cron expression or a window opening rather than the interval.
+     */
+    pub fn next_run(
+        &self,
+        now: DateTime<Local>,
+        interval: std::time::Duration,
+    ) -> Option<(DateTime<Local>, bool)> {
+        if let Some(cron) = &self.cron {
+            let limit = now + Duration::days(SEARCH_DAYS);
+            let mut next = cron.next_after(now)?;
+            while !self.in_window(next) {
+                next = cron.next_after(next)?;
+                if next > limit {
+                    return None;
+                }
+            }
+            return Some((next, true));
+        }
+        if self.windows.is_empty() {
+            return Some((now + Duration::from_std(interval).ok()?, false));
+        }
+        if let Some(window) = self.window_at(now) {
+            let interval = window.interval.unwrap_or(interval);
+            let next = now + Duration::from_std(interval).ok()?;
+            if self.in_window(next) {
+                return Some((next, false));
+            }
+            return Some((self.next_window_start(next)?, true));
+        }
+        Some((self.next_window_start(now)?, true))
+    }
+}
+
+/**
+ * How long transferring 'bytes' should take at least to stay under 'rate' bytes per second.
+ */
+pub fn transfer_time(bytes: u64, rate: u64) -> std::time::Duration {
+    std::time::Duration::from_secs_f64(bytes as f64 / rate.max(1) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_settings() {
+        let window = Window::parse("22:00-06:00=5,10M/s").unwrap();
+        assert_eq!(window.interval, Some(std::time::Duration::from_secs(5)));
+        assert_eq!(window.rate, Some(10 * 1024 * 1024));
+        let window = Window::parse("22:00-06:00=1K/s").unwrap();
+        assert_eq!(window.interval, None);
+        assert_eq!(window.rate, Some(1024));
+        let window = Window::parse("22:00-06:00").unwrap();
+        assert_eq!((window.interval, window.rate), (None, None));
+        for invalid in [
+            "22:00-06:00=",
+            "22:00-06:00=0",
+            "22:00-06:00=5,6",
+            "22:00-06:00=0/s",
+        ] {
+            assert!(Window::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn rate_of_the_open_window() {
+        let mut schedule = Schedule::default();
+        schedule.add_window(Window::parse("00:00-12:00=2K/s").unwrap());
+        schedule.add_window(Window::parse("12:00-23:59").unwrap());
+        let at = |hour| {
+            Local
+                .from_local_datetime(
+                    &NaiveDate::from_ymd_opt(2024, 1, 15)
+                        .unwrap()
+                        .and_hms_opt(hour, 0, 0)
+                        .unwrap(),
+                )
+                .unwrap()
+        };
+        assert_eq!(schedule.rate_at(at(6)), Some(2048));
+        assert_eq!(schedule.rate_at(at(18)), None);
+    }
+
+    #[test]
+    fn transfer_time_at_rate() {
+        assert_eq!(transfer_time(2048, 1024), std::time::Duration::from_secs(2));
+        assert_eq!(transfer_time(0, 1024), std::time::Duration::ZERO);
+    }
+}
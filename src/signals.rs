@@ -0,0 +1,66 @@
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::flag;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/**
+ * Signals received since they were last checked. The handlers only set flags; the main loop acts on
+ * them between files.
+ *
+ *   SIGINT, SIGTERM  Finish the current file, flush the logs and exit.
+ *   SIGUSR1          Pause sweeps.
+ *   SIGUSR2          Resume sweeps.
+ *   SIGHUP           Reload the configuration.
+ */
+#[derive(Default)]
+pub struct Signals {
+    shutdown: Arc<AtomicBool>,
+    pause: Arc<AtomicBool>,
+    resume: Arc<AtomicBool>,
+    reload: Arc<AtomicBool>,
+}
+
+impl Signals {
+    pub fn register() -> io::Result<Signals> {
+        let signals = Signals::default();
+        for signal in [SIGINT, SIGTERM] {
+            flag::register(signal, Arc::clone(&signals.shutdown))?;
+        }
+        signals.register_unix()?;
+        Ok(signals)
+    }
+
+    #[cfg(unix)]
+    fn register_unix(&self) -> io::Result<()> {
+        use signal_hook::consts::{SIGHUP, SIGUSR1, SIGUSR2};
+        flag::register(SIGUSR1, Arc::clone(&self.pause))?;
+        flag::register(SIGUSR2, Arc::clone(&self.resume))?;
+        flag::register(SIGHUP, Arc::clone(&self.reload))?;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn register_unix(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /**
+     * Stays true once a shutdown signal has been received.
+     */
+    pub fn shutdown_requested(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst)
+    }
+
+    pub fn take_pause(&self) -> bool {
+        self.pause.swap(false, Ordering::SeqCst)
+    }
+
+    pub fn take_resume(&self) -> bool {
+        self.resume.swap(false, Ordering::SeqCst)
+    }
+
+    pub fn take_reload(&self) -> bool {
+        self.reload.swap(false, Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,300 @@
+use super::Level;
+use chrono::{offset, SecondsFormat};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+#[cfg(unix)]
+use std::path::PathBuf;
+
+/**
+ * The enterprise number used for structured data in syslog messages. 32473 is reserved for
+ * documentation and private use by RFC 5612.
+ */
+const SD_ID: &str = "mover@32473";
+
+/**
+ * A log output other than the console and the log file. Each sink has its own level filter.
+ */
+pub trait Sink {
+    fn level(&self) -> Level;
+    fn name(&self) -> String;
+    fn send(&mut self, level: Level, msg: &str, fields: &[(&str, String)]) -> io::Result<()>;
+}
+
+/**
+ * Where syslog messages go, along with the socket they are sent from. The socket is opened once and
+ * kept for the life of the sink.
+ */
+enum SyslogTarget {
+    #[cfg(unix)]
+    Unix(PathBuf, UnixDatagram),
+    Udp(SocketAddr, UdpSocket),
+}
+
+/**
+ * Sends RFC 5424 syslog messages over a Unix datagram socket (e.g. /dev/log) or UDP.
+ */
+pub struct Syslog {
+    target: SyslogTarget,
+    level: Level,
+    hostname: String,
+}
+
+impl Syslog {
+    /**
+     * Creates a syslog sink from "unix:<socket-path>" or "udp:<host>:<port>".
+     */
+    pub fn new(target: &str, level: Level) -> Result<Syslog, String> {
+        let target = if let Some(addr) = target.strip_prefix("udp:") {
+            let addr = match addr.to_socket_addrs().map(|mut addrs| addrs.next()) {
+                Ok(Some(addr)) => addr,
+                _ => return Err(format!("Invalid syslog address \"{}\".", addr)),
+            };
+            let bind = if addr.is_ipv4() {
+                "0.0.0.0:0"
+            } else {
+                "[::]:0"
+            };
+            match UdpSocket::bind(bind) {
+                Ok(socket) => SyslogTarget::Udp(addr, socket),
+                Err(e) => return Err(format!("Unable to open a syslog socket. {}", e)),
+            }
+        } else if let Some(path) = target.strip_prefix("unix:") {
+            unix_target(path)?
+        } else {
+            return Err(format!(
+                "Invalid syslog target \"{}\". Use unix:<path> or udp:<host>:<port>.",
+                target
+            ));
+        };
+        Ok(Syslog {
+            target,
+            level,
+            hostname: crate::template::hostname(),
+        })
+    }
+
+    /**
+     * Formats a message as RFC 5424 with the user facility.
+     */
+    fn format(&self, level: Level, msg: &str, fields: &[(&str, String)]) -> String {
+        let severity = match level {
+            Level::Error => 3,
+            Level::Warn => 4,
+            Level::Info => 6,
+            Level::Debug | Level::Trace => 7,
+        };
+        let timestamp = offset::Local::now().to_rfc3339_opts(SecondsFormat::Micros, false);
+        let structured_data = if fields.is_empty() {
+            String::from("-")
+        } else {
+            let params: Vec<String> = fields
+                .iter()
+                .map(|(name, value)| format!(" {}=\"{}\"", name.to_lowercase(), sd_escape(value)))
+                .collect();
+            format!("[{}{}]", SD_ID, params.concat())
+        };
+        format!(
+            "<{}>1 {} {} mover {} - {} {}",
+            8 + severity,
+            timestamp,
+            self.hostname,
+            std::process::id(),
+            structured_data,
+            msg.trim_end().replace('\n', " ")
+        )
+    }
+}
+
+#[cfg(unix)]
+fn unix_target(path: &str) -> Result<SyslogTarget, String> {
+    match UnixDatagram::unbound() {
+        Ok(socket) => Ok(SyslogTarget::Unix(PathBuf::from(path), socket)),
+        Err(e) => Err(format!("Unable to open a syslog socket. {}", e)),
+    }
+}
+
+#[cfg(not(unix))]
+fn unix_target(_path: &str) -> Result<SyslogTarget, String> {
+    Err(String::from(
+        "Unix sockets are not supported on this platform. Use udp:<host>:<port>.",
+    ))
+}
+
+impl Sink for Syslog {
+    fn level(&self) -> Level {
+        self.level
+    }
+
+    fn name(&self) -> String {
+        match &self.target {
+            #[cfg(unix)]
+            SyslogTarget::Unix(path, _) => format!("syslog unix:{}", path.to_str().unwrap()),
+            SyslogTarget::Udp(addr, _) => format!("syslog udp:{}", addr),
+        }
+    }
+
+    fn send(&mut self, level: Level, msg: &str, fields: &[(&str, String)]) -> io::Result<()> {
+        let message = self.format(level, msg, fields);
+        match &self.target {
+            #[cfg(unix)]
+            SyslogTarget::Unix(path, socket) => {
+                socket.send_to(message.as_bytes(), path)?;
+            }
+            SyslogTarget::Udp(addr, socket) => {
+                socket.send_to(message.as_bytes(), addr)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Escapes a structured data parameter value as required by RFC 5424.
+ */
+fn sd_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(']', "\\]")
+}
+
+/**
+ * The socket the systemd journal listens on for its native protocol.
+ */
+#[cfg(unix)]
+pub const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/**
+ * Sends entries to the systemd journal using its native protocol, with each field as a separate
+ * journal field.
+ */
+#[cfg(unix)]
+pub struct Journald {
+    path: PathBuf,
+    socket: UnixDatagram,
+    level: Level,
+}
+
+#[cfg(unix)]
+impl Journald {
+    /**
+     * Creates a journal sink that sends to the socket at 'path'.
+     */
+    pub fn new(path: &str, level: Level) -> Result<Journald, String> {
+        match UnixDatagram::unbound() {
+            Ok(socket) => Ok(Journald {
+                path: PathBuf::from(path),
+                socket,
+                level,
+            }),
+            Err(e) => Err(format!("Unable to open a journald socket. {}", e)),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Sink for Journald {
+    fn level(&self) -> Level {
+        self.level
+    }
+
+    fn name(&self) -> String {
+        format!("journald {}", self.path.to_str().unwrap())
+    }
+
+    fn send(&mut self, level: Level, msg: &str, fields: &[(&str, String)]) -> io::Result<()> {
+        let priority = match level {
+            Level::Error => "3",
+            Level::Warn => "4",
+            Level::Info => "6",
+            Level::Debug | Level::Trace => "7",
+        };
+        let mut datagram = Vec::new();
+        journal_field(&mut datagram, "MESSAGE", msg.trim_end());
+        journal_field(&mut datagram, "PRIORITY", priority);
+        journal_field(&mut datagram, "SYSLOG_IDENTIFIER", "mover");
+        for (name, value) in fields {
+            journal_field(&mut datagram, name, value);
+        }
+        self.socket.send_to(&datagram, &self.path)?;
+        Ok(())
+    }
+}
+
+/**
+ * Appends a field in the journal's native format. Values with new lines are sent with their
+ * length in front instead of being terminated by a new line.
+ */
+#[cfg(unix)]
+fn journal_field(datagram: &mut Vec<u8>, name: &str, value: &str) {
+    datagram.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        datagram.push(b'\n');
+        datagram.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        datagram.push(b'=');
+    }
+    datagram.extend_from_slice(value.as_bytes());
+    datagram.push(b'\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn syslog_sends_over_udp() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let target = format!("udp:{}", server.local_addr().unwrap());
+        let mut syslog = Syslog::new(&target, Level::Info).unwrap();
+        let fields = [("SOURCE", String::from("a \"b\"]"))];
+        syslog.send(Level::Warn, "First\n", &fields).unwrap();
+        syslog.send(Level::Error, "Second", &[]).unwrap();
+
+        let mut buf = [0; 1024];
+        let len = server.recv(&mut buf).unwrap();
+        let message = String::from_utf8_lossy(&buf[..len]).to_string();
+        assert!(message.starts_with("<12>1 "));
+        assert!(message.ends_with(" [mover@32473 source=\"a \\\"b\\\"\\]\"] First"));
+        let len = server.recv(&mut buf).unwrap();
+        let message = String::from_utf8_lossy(&buf[..len]).to_string();
+        assert!(message.starts_with("<11>1 "));
+        assert!(message.ends_with(" - Second"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn journald_sends_native_fields() {
+        let dir = std::env::temp_dir().join(format!("mover-journald-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("socket");
+        let _ = std::fs::remove_file(&path);
+        let server = UnixDatagram::bind(&path).unwrap();
+        server
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut journald = Journald::new(path.to_str().unwrap(), Level::Info).unwrap();
+        let fields = [("BYTES", String::from("42"))];
+        journald
+            .send(Level::Info, "Line 1\nLine 2\n", &fields)
+            .unwrap();
+        journald.send(Level::Error, "Again", &[]).unwrap();
+
+        let mut buf = [0; 1024];
+        let len = server.recv(&mut buf).unwrap();
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&13u64.to_le_bytes());
+        expected.extend_from_slice(b"Line 1\nLine 2\n");
+        expected.extend_from_slice(b"PRIORITY=6\nSYSLOG_IDENTIFIER=mover\nBYTES=42\n");
+        assert_eq!(&buf[..len], &expected[..]);
+        let len = server.recv(&mut buf).unwrap();
+        assert!(buf[..len].starts_with(b"MESSAGE=Again\nPRIORITY=3\n"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
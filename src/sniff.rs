@@ -0,0 +1,264 @@
+use serde_json::Value;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/**
+ * The number of bytes read from the start of a file to detect its type.
+ */
+const SNIFF_LEN: usize = 1024;
+
+/**
+ * Magic byte signatures and the content type they identify.
+ */
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"%PDF-", "pdf"),
+    (b"PK\x03\x04", "zip"),
+    (b"PK\x05\x06", "zip"),
+    (b"\x89PNG\r\n\x1a\n", "png"),
+    (b"\x1f\x8b", "gzip"),
+    (b"\xff\xd8\xff", "jpeg"),
+    (b"GIF87a", "gif"),
+    (b"GIF89a", "gif"),
+    (b"II*\x00", "tiff"),
+    (b"MM\x00*", "tiff"),
+    (b"Rar!\x1a\x07", "rar"),
+    (b"7z\xbc\xaf\x27\x1c", "7z"),
+    (b"BZh", "bzip2"),
+    (b"\xfd7zXZ\x00", "xz"),
+    (b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1", "ole"),
+    (b"\x7fELF", "elf"),
+];
+
+/**
+ * Detects the type of a file from its first bytes. Returns None for empty or unreadable files.
+ */
+pub fn detect(path: &Path) -> Option<&'static str> {
+    let mut buf = Vec::with_capacity(SNIFF_LEN);
+    match File::open(path).and_then(|file| file.take(SNIFF_LEN as u64).read_to_end(&mut buf)) {
+        Ok(len) if len > 0 => {}
+        _ => return None,
+    };
+    for (signature, content_type) in SIGNATURES {
+        if buf.starts_with(signature) {
+            return Some(content_type);
+        }
+    }
+    if is_exe(&buf) {
+        return Some("exe");
+    }
+    // A shorter read means the whole file is in the buffer.
+    detect_text(&buf, buf.len() < SNIFF_LEN)
+}
+
+/**
+ * Windows executables start with "MZ", and the DOS header points to a "PE\0\0" header. Text that
+ * happens to start with "MZ" has no such header.
+ */
+fn is_exe(buf: &[u8]) -> bool {
+    if !buf.starts_with(b"MZ") || buf.len() < 0x40 {
+        return false;
+    }
+    let offset = u32::from_le_bytes([buf[0x3c], buf[0x3d], buf[0x3e], buf[0x3f]]) as usize;
+    match buf.get(offset..offset.saturating_add(4)) {
+        Some(header) => header == b"PE\0\0",
+        // The PE header can be past the bytes read, but text will not point that far.
+        None => offset >= buf.len() && buf[..0x40].contains(&0),
+    }
+}
+
+/**
+ * Text heuristics. Anything that is valid UTF-8 without NUL bytes is text. Text is JSON or XML
+ * when it parses as such, as far as it was read, and CSV when the first complete lines all contain
+ * the same, non-zero number of separators. 'complete' is true when 'buf' holds the whole file.
+ */
+fn detect_text(buf: &[u8], complete: bool) -> Option<&'static str> {
+    if buf.contains(&0) {
+        return Some("binary");
+    }
+    let text = match std::str::from_utf8(buf) {
+        Ok(text) => text,
+        // The read may have cut a multi-byte character in half.
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&buf[..e.valid_up_to()]).unwrap(),
+        Err(_) => return Some("binary"),
+    };
+    let trimmed = text.trim_start_matches('\u{feff}').trim_start();
+    if (trimmed.starts_with('{') || trimmed.starts_with('[')) && is_json(trimmed, complete) {
+        return Some("json");
+    }
+    if trimmed.starts_with('<') && is_xml(trimmed, complete) {
+        return Some("xml");
+    }
+    let mut lines: Vec<&str> = text.lines().collect();
+    if lines.len() > 1 && !text.ends_with('\n') {
+        // The last line is probably incomplete.
+        lines.pop();
+    }
+    for separator in &[',', ';', '\t'] {
+        let counts: Vec<usize> = lines
+            .iter()
+            .map(|l| l.matches(*separator).count())
+            .collect();
+        if !counts.is_empty() && counts[0] > 0 && counts.iter().all(|c| *c == counts[0]) {
+            return Some("csv");
+        }
+    }
+    Some("text")
+}
+
+/**
+ * Returns true when 'text' is a JSON document, or the start of one if it is not 'complete'.
+ */
+fn is_json(text: &str, complete: bool) -> bool {
+    match serde_json::from_str::<Value>(text) {
+        Ok(_) => true,
+        Err(e) => !complete && e.is_eof(),
+    }
+}
+
+/**
+ * Returns true when 'text' is a well-formed XML document, or the start of one if it is not
+ * 'complete': a single root element with matching tags, optionally after a declaration, comments
+ * and a doctype.
+ */
+fn is_xml(text: &str, complete: bool) -> bool {
+    let mut open: Vec<&str> = Vec::new();
+    let mut root_closed = false;
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        // Only whitespace is allowed outside the root element.
+        if open.is_empty() && !rest[..start].trim().is_empty() {
+            return false;
+        }
+        rest = &rest[start..];
+        let (end_marker, skip) = if rest.starts_with("<?") {
+            ("?>", true)
+        } else if rest.starts_with("<!--") {
+            ("-->", true)
+        } else if rest.starts_with("<![CDATA[") {
+            ("]]>", true)
+        } else if rest.starts_with("<!") {
+            (">", true)
+        } else {
+            (">", false)
+        };
+        let end = match tag_end(rest, end_marker) {
+            Some(end) => end,
+            // The tag was cut off by the end of the read.
+            None => return !complete,
+        };
+        let tag = &rest[..end];
+        rest = &rest[end..];
+        if skip {
+            continue;
+        }
+        let inner = &tag[1..tag.len() - 1];
+        if let Some(name) = inner.strip_prefix('/') {
+            if open.pop() != Some(name.trim_end()) {
+                return false;
+            }
+            root_closed = open.is_empty();
+            continue;
+        }
+        if root_closed {
+            return false;
+        }
+        let name = inner
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("");
+        let valid_start = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_' || c == ':');
+        if !valid_start {
+            return false;
+        }
+        if inner.ends_with('/') {
+            root_closed = open.is_empty();
+        } else {
+            open.push(name);
+        }
+    }
+    if !open.is_empty() {
+        return !complete;
+    }
+    root_closed && rest.trim().is_empty()
+}
+
+/**
+ * The index just past 'end_marker' for the tag at the start of 'text', skipping over quoted
+ * attribute values in element tags.
+ */
+fn tag_end(text: &str, end_marker: &str) -> Option<usize> {
+    if end_marker != ">" || text.starts_with("<!") {
+        // A doctype can hold declarations of its own between brackets.
+        let end_marker = match text.find(['[', '>']) {
+            Some(i) if end_marker == ">" && text[i..].starts_with('[') => "]>",
+            _ => end_marker,
+        };
+        return text[1..].find(end_marker).map(|i| 1 + i + end_marker.len());
+    }
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '>' => return Some(i + 1),
+            None => {}
+        }
+    }
+    None
+}
+
+/**
+ * Returns the content type that a file extension claims to be, if it is one we can detect.
+ */
+pub fn expected_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    let content_type = match ext.as_str() {
+        "pdf" => "pdf",
+        "zip" | "docx" | "xlsx" | "pptx" | "jar" | "odt" | "ods" => "zip",
+        "png" => "png",
+        "gz" | "tgz" => "gzip",
+        "jpg" | "jpeg" => "jpeg",
+        "gif" => "gif",
+        "tif" | "tiff" => "tiff",
+        "rar" => "rar",
+        "7z" => "7z",
+        "bz2" => "bzip2",
+        "xz" => "xz",
+        "doc" | "xls" | "ppt" | "msg" => "ole",
+        "exe" | "dll" => "exe",
+        "xml" => "xml",
+        "json" => "json",
+        "csv" | "tsv" => "csv",
+        "txt" | "log" => "text",
+        _ => return None,
+    };
+    Some(content_type)
+}
+
+/**
+ * Returns true when a file's extension claims a different type than its content. Files with
+ * unknown extensions or undetectable content never mismatch.
+ */
+pub fn is_mismatch(path: &Path, detected: Option<&str>) -> bool {
+    match (expected_type(path), detected) {
+        (Some(expected), Some(detected)) => !compatible(expected, detected),
+        _ => false,
+    }
+}
+
+fn compatible(expected: &str, detected: &str) -> bool {
+    match expected {
+        // A CSV with a single column, or a text file that happens to look like one.
+        "csv" => matches!(detected, "csv" | "text"),
+        // JSON, XML and CSV are all text.
+        "text" => matches!(detected, "csv" | "text" | "json" | "xml"),
+        // JSON and XML files are only detected as such when they parse.
+        "json" | "xml" => detected == expected,
+        _ => detected == expected,
+    }
+}
@@ -0,0 +1,59 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/**
+ * Bytes available to this user on the file system holding 'path'. The nearest existing ancestor
+ * is used, since templated destinations may not exist yet.
+ */
+pub fn available(path: &Path) -> io::Result<u64> {
+    let mut dir = path;
+    while !dir.exists() {
+        dir = match dir.parent() {
+            Some(parent) => parent,
+            None => break,
+        };
+    }
+    fs2::available_space(dir)
+}
+
+/**
+ * The total size of every file under 'dir'. Unreadable entries are left out.
+ */
+pub fn usage(dir: &Path) -> u64 {
+    files(dir).iter().map(|(_, _, len)| len).sum()
+}
+
+/**
+ * Every file under 'dir' with its modified time and size, oldest first.
+ */
+pub fn oldest_files(dir: &Path) -> Vec<(PathBuf, SystemTime, u64)> {
+    let mut files = files(dir);
+    files.sort_by_key(|(_, mtime, _)| *mtime);
+    files
+}
+
+fn files(dir: &Path) -> Vec<(PathBuf, SystemTime, u64)> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+            } else if metadata.is_file() {
+                let mtime = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                files.push((entry.path(), mtime, metadata.len()));
+            }
+        }
+    }
+    files
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+/**
+ * Counts what happened during a cycle, or since start up when cycles are added together.
+ */
+#[derive(Default, Clone)]
+pub struct Stats {
+    pub cycles: u64,
+    pub moved: u64,
+    pub copied: u64,
+    pub bytes: u64,
+    pub identical: u64,
+    pub blocked: u64,
+    pub failed: u64,
+    pub duration: Duration,
+}
+
+impl Stats {
+    /**
+     * A cycle is worth a summary when something was transferred or failed. Identical and blocked
+     * files are seen again every cycle, so they alone do not count.
+     */
+    pub fn is_empty(&self) -> bool {
+        self.moved == 0 && self.copied == 0 && self.failed == 0
+    }
+
+    pub fn add(&mut self, other: &Stats) {
+        self.cycles += other.cycles;
+        self.moved += other.moved;
+        self.copied += other.copied;
+        self.bytes += other.bytes;
+        self.identical += other.identical;
+        self.blocked += other.blocked;
+        self.failed += other.failed;
+        self.duration += other.duration;
+    }
+
+    /**
+     * Bytes transferred per second of time spent in cycles.
+     */
+    pub fn throughput(&self) -> f64 {
+        let secs = self.duration.as_secs_f64();
+        if secs > 0.0 {
+            self.bytes as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} moved, {} copied, {} transferred, {} identical, {} blocked by no-overwrite, {} failed in {:.2}s ({}/s)",
+            self.moved,
+            self.copied,
+            format_bytes(self.bytes as f64),
+            self.identical,
+            self.blocked,
+            self.failed,
+            self.duration.as_secs_f64(),
+            format_bytes(self.throughput())
+        )
+    }
+}
+
+/**
+ * Formats a number of bytes with a binary unit, e.g. "1.50 MiB".
+ */
+pub fn format_bytes(bytes: f64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{:.0} {}", value, units[unit])
+    } else {
+        format!("{:.2} {}", value, units[unit])
+    }
+}
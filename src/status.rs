@@ -0,0 +1,210 @@
+use super::http;
+use chrono::{DateTime, Local, SecondsFormat};
+use serde_json::{json, Value};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum State {
+    Idle,
+    Paused,
+    Scanning,
+    Transferring,
+    WaitingForPath,
+}
+
+impl State {
+    pub fn as_str(&self) -> &str {
+        match self {
+            State::Idle => "idle",
+            State::Paused => "paused",
+            State::Scanning => "scanning",
+            State::Transferring => "transferring",
+            State::WaitingForPath => "waiting-for-path",
+        }
+    }
+}
+
+/**
+ * What the job is doing right now, shared with the control endpoint. Commands received by the
+ * endpoint are left here for the main loop to pick up.
+ */
+pub struct Status {
+    pub label: String,
+    pub from_dir: String,
+    pub to_dir: String,
+    pub state: State,
+    pub current_file: Option<String>,
+    pub current_file_bytes: u64,
+    pub cycle_files_done: u64,
+    pub cycle_bytes_done: u64,
+    pub last_error: Option<(DateTime<Local>, String)>,
+    pub next_run: Option<DateTime<Local>>,
+    pub paused: bool,
+    pub sweep_requested: bool,
+    pub reload_requested: bool,
+}
+
+impl Default for Status {
+    fn default() -> Status {
+        Status {
+            label: String::new(),
+            from_dir: String::new(),
+            to_dir: String::new(),
+            state: State::Idle,
+            current_file: None,
+            current_file_bytes: 0,
+            cycle_files_done: 0,
+            cycle_bytes_done: 0,
+            last_error: None,
+            next_run: None,
+            paused: false,
+            sweep_requested: false,
+            reload_requested: false,
+        }
+    }
+}
+
+impl Status {
+    pub fn to_json(&self) -> Value {
+        let time = |t: &DateTime<Local>| t.to_rfc3339_opts(SecondsFormat::Secs, false);
+        json!({
+            "version": super::VERSION,
+            "jobs": [{
+                "label": self.label,
+                "from": self.from_dir,
+                "to": self.to_dir,
+                "state": self.state.as_str(),
+                "paused": self.paused,
+                "current_file": self.current_file,
+                "current_file_bytes": self.current_file_bytes,
+                "cycle_files_done": self.cycle_files_done,
+                "cycle_bytes_done": self.cycle_bytes_done,
+                "last_error": self.last_error.as_ref().map(|(t, msg)| json!({
+                    "time": time(t),
+                    "message": msg,
+                })),
+                "next_run": self.next_run.as_ref().map(time),
+            }],
+        })
+    }
+}
+
+/**
+ * Starts the control endpoint on "<address>:<port>" (localhost HTTP) or "unix:<socket-path>".
+ *
+ *   GET  /status   The status of every job as JSON.
+ *   POST /pause    Stops starting new sweeps until resumed.
+ *   POST /resume   Resumes sweeps.
+ *   POST /sweep    Starts a sweep now instead of waiting for the next one.
+ *   POST /reload   Reloads the configuration file (see /config).
+ */
+/**
+ * Checks that a /control address is local, so a bad one is reported when the options are read. The
+ * endpoint has no authentication and can pause and reload the job, so it must not be reachable
+ * from other machines.
+ */
+pub fn validate_addr(addr: &str) -> Result<(), String> {
+    if addr.starts_with("unix:") {
+        return Ok(());
+    }
+    let addrs: Vec<_> = addr
+        .to_socket_addrs()
+        .map_err(|e| format!("Invalid /control address \"{}\": {}", addr, e))?
+        .collect();
+    if addrs.is_empty() || addrs.iter().any(|a| !a.ip().is_loopback()) {
+        return Err(format!(
+            "The /control address \"{}\" is not a loopback address, use e.g. 127.0.0.1:<port>.",
+            addr
+        ));
+    }
+    Ok(())
+}
+
+pub fn serve(addr: &str, status: Arc<Mutex<Status>>) -> io::Result<()> {
+    if let Some(path) = addr.strip_prefix("unix:") {
+        return serve_unix(path, status);
+    }
+    let listener = TcpListener::bind(addr)?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // A client that misbehaves only loses its own response.
+            let _ = stream.set_read_timeout(Some(http::TIMEOUT));
+            let _ = stream.set_write_timeout(Some(http::TIMEOUT));
+            let _ = respond(&stream, &status);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(unix)]
+fn serve_unix(path: &str, status: Arc<Mutex<Status>>) -> io::Result<()> {
+    // A socket left behind by a previous run would stop the bind. Anything else at the path, or a
+    // socket another process still listens on, is left alone and the bind fails.
+    let stale = std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket())
+        && UnixStream::connect(path).is_err();
+    if stale {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(http::TIMEOUT));
+            let _ = stream.set_write_timeout(Some(http::TIMEOUT));
+            let _ = respond(&stream, &status);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve_unix(_path: &str, _status: Arc<Mutex<Status>>) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Unix sockets are not supported on this platform.",
+    ))
+}
+
+fn respond<S>(stream: S, status: &Arc<Mutex<Status>>) -> io::Result<()>
+where
+    S: Read + Write + Copy,
+{
+    let request = http::read_request(stream)?;
+    let mut status = status.lock().unwrap();
+    let (code, body) = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/status") => ("200 OK", status.to_json()),
+        ("POST", "/pause") => {
+            status.paused = true;
+            ("200 OK", json!({ "ok": true, "command": "pause" }))
+        }
+        ("POST", "/resume") => {
+            status.paused = false;
+            ("200 OK", json!({ "ok": true, "command": "resume" }))
+        }
+        ("POST", "/sweep") => {
+            status.sweep_requested = true;
+            ("200 OK", json!({ "ok": true, "command": "sweep" }))
+        }
+        ("POST", "/reload") => {
+            status.reload_requested = true;
+            ("200 OK", json!({ "ok": true, "command": "reload" }))
+        }
+        (_, "/status") | (_, "/pause") | (_, "/resume") | (_, "/sweep") | (_, "/reload") => (
+            "405 Method Not Allowed",
+            json!({ "ok": false, "error": "method not allowed" }),
+        ),
+        _ => (
+            "404 Not Found",
+            json!({ "ok": false, "error": "not found" }),
+        ),
+    };
+    drop(status);
+    let body = format!("{}\n", body);
+    http::write_response(stream, code, "application/json", &body)
+}
@@ -0,0 +1,157 @@
+use chrono::{DateTime, Local};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use std::{env, fs};
+
+/**
+ * Everything a destination template can refer to for a single file.
+ */
+pub struct Context<'a> {
+    pub to_dir: &'a Path,
+    pub child_dir: &'a Path,
+    pub file_name: &'a str,
+    pub mtime: SystemTime,
+    pub now: DateTime<Local>,
+    pub label: &'a str,
+    pub content_type: &'a str,
+    pub hostname: &'a str,
+}
+
+/**
+ * Renders a destination template such as "{to}/{yyyy}/{mm}/{dd}/{relpath}" into a file path.
+ * Templates that do not start with {to} are placed under the 'to' directory.
+ *
+ * Placeholders:
+ *   {to}            The 'to' directory.
+ *   {relpath}       The file path relative to the 'from' directory.
+ *   {reldir}        The directory of the file relative to the 'from' directory.
+ *   {name}          The file name.
+ *   {stem}          The file name without its extension.
+ *   {ext}           The file extension, empty if there is none.
+ *   {yyyy} {mm} {dd} {HH} {MM} {SS}
+ *                   Parts of the move time.
+ *   {now:<format>}  The move time in a strftime format, e.g. {now:%Y%m%d}.
+ *   {mtime:<format>}
+ *                   The file's modification time in a strftime format.
+ *   {label}         The source label (see /label).
+ *   {type}          The detected content type, e.g. pdf, zip or csv.
+ *   {hostname}      The name of this machine.
+ */
+pub fn render(template: &str, ctx: &Context) -> Result<PathBuf, String> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => {
+                return Err(format!(
+                    "Unclosed placeholder in template \"{}\".",
+                    template
+                ))
+            }
+        };
+        rendered.push_str(&placeholder(&rest[start + 1..end], ctx)?);
+        rest = &rest[end + 1..];
+    }
+    rendered.push_str(rest);
+
+    // A missing extension would otherwise leave names like "report." behind.
+    let rendered = rendered.trim_end_matches('.');
+    if rendered.is_empty() {
+        return Err(format!(
+            "The template \"{}\" produced an empty path.",
+            template
+        ));
+    }
+    if template.starts_with("{to}") {
+        Ok(PathBuf::from(rendered))
+    } else {
+        Ok(ctx.to_dir.join(rendered))
+    }
+}
+
+/**
+ * Checks a template for unknown placeholders before it is used.
+ */
+pub fn validate(template: &str) -> Result<(), String> {
+    let ctx = Context {
+        to_dir: Path::new("to"),
+        child_dir: Path::new("dir"),
+        file_name: "file.txt",
+        mtime: SystemTime::now(),
+        now: Local::now(),
+        label: "label",
+        content_type: "text",
+        hostname: "host",
+    };
+    render(template, &ctx).map(|_| ())
+}
+
+fn placeholder(name: &str, ctx: &Context) -> Result<String, String> {
+    let file = Path::new(ctx.file_name);
+    let value = match name {
+        "to" => ctx.to_dir.to_str().unwrap().to_string(),
+        "relpath" => ctx
+            .child_dir
+            .join(ctx.file_name)
+            .to_str()
+            .unwrap()
+            .to_string(),
+        "reldir" => ctx.child_dir.to_str().unwrap().to_string(),
+        "name" => ctx.file_name.to_string(),
+        "stem" => os_str(file.file_stem()),
+        "ext" => os_str(file.extension()),
+        "yyyy" => ctx.now.format("%Y").to_string(),
+        "mm" => ctx.now.format("%m").to_string(),
+        "dd" => ctx.now.format("%d").to_string(),
+        "HH" => ctx.now.format("%H").to_string(),
+        "MM" => ctx.now.format("%M").to_string(),
+        "SS" => ctx.now.format("%S").to_string(),
+        "label" => ctx.label.to_string(),
+        "type" => ctx.content_type.to_string(),
+        "hostname" => ctx.hostname.to_string(),
+        _ => {
+            if let Some(format) = name.strip_prefix("now:") {
+                time_format(&ctx.now, format)?
+            } else if let Some(format) = name.strip_prefix("mtime:") {
+                time_format(&DateTime::<Local>::from(ctx.mtime), format)?
+            } else {
+                return Err(format!("Unknown placeholder \"{{{}}}\".", name));
+            }
+        }
+    };
+    Ok(value)
+}
+
+fn time_format(time: &DateTime<Local>, format: &str) -> Result<String, String> {
+    use std::fmt::Write;
+    let mut value = String::new();
+    match write!(value, "{}", time.format(format)) {
+        Ok(()) => Ok(value),
+        Err(_) => Err(format!("Invalid time format \"{}\".", format)),
+    }
+}
+
+fn os_str(value: Option<&std::ffi::OsStr>) -> String {
+    value
+        .map(|v| v.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/**
+ * Returns the name of this machine, falling back to "localhost" if it cannot be found.
+ */
+pub fn hostname() -> String {
+    for var in &["COMPUTERNAME", "HOSTNAME"] {
+        if let Ok(name) = env::var(var) {
+            if !name.is_empty() {
+                return name;
+            }
+        }
+    }
+    match fs::read_to_string("/etc/hostname") {
+        Ok(name) if !name.trim().is_empty() => name.trim().to_string(),
+        _ => String::from("localhost"),
+    }
+}
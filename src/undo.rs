@@ -0,0 +1,222 @@
+use super::fsutil;
+use super::records;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/**
+ * Reverses transfer records, given newest first. In a dry run nothing is changed; the paths that
+ * would be moved are tracked instead, so later records are checked against the planned state.
+ */
+pub struct Undo {
+    dry_run: bool,
+    gone: HashSet<PathBuf>,
+    added: HashSet<PathBuf>,
+}
+
+impl Undo {
+    pub fn new(dry_run: bool) -> Undo {
+        Undo {
+            dry_run,
+            gone: HashSet::new(),
+            added: HashSet::new(),
+        }
+    }
+
+    /**
+     * Reverses one record. Returns what was done, or the conflict that stopped it. None for records
+     * with nothing to undo, including transfers that were already undone.
+     */
+    pub fn apply(&mut self, record: &Value) -> Option<Result<String, String>> {
+        let path = |field| PathBuf::from(records::text(record, field));
+        let bytes = record["bytes"].as_u64();
+        match records::text(record, "action") {
+            // Already put back, e.g. by an earlier undo.
+            "moved" if self.exists(&path("from")) && !self.exists(&path("to")) => None,
+            "copied" if !self.exists(&path("to")) => None,
+            "moved" => {
+                let kept = record["kept"].as_str().map(PathBuf::from);
+                Some(self.moved(&path("from"), &path("to"), bytes, kept))
+            }
+            "copied" => Some(self.copied(&path("to"), bytes)),
+            "versioned" => Some(self.versioned(&path("path"), &path("version"))),
+            "removed_dir" => self.removed_dir(&path("path")),
+            _ => None,
+        }
+    }
+
+    /**
+     * Puts a moved file back: the original kept by /disposal if there is one, otherwise the file in
+     * the 'to' directory.
+     */
+    fn moved(
+        &mut self,
+        from: &Path,
+        to: &Path,
+        bytes: Option<u64>,
+        kept: Option<PathBuf>,
+    ) -> Result<String, String> {
+        if self.exists(from) {
+            return Err(format!(
+                "\"{}\" exists again. \"{}\" was left in place.",
+                from.display(),
+                to.display()
+            ));
+        }
+        if self.exists(to) && !self.unchanged(to, bytes) {
+            return Err(format!(
+                "\"{}\" has changed since it was moved and was left in place.",
+                to.display()
+            ));
+        }
+        match kept.filter(|kept| self.exists(kept)) {
+            Some(kept) => {
+                // The delivered file is a copy of the kept original, so it goes.
+                let removed = if self.exists(to) {
+                    self.remove(to)?;
+                    let removed = if self.dry_run { "remove" } else { "removed" };
+                    format!(" and {} \"{}\"", removed, to.display())
+                } else {
+                    String::new()
+                };
+                self.move_path(&kept, from)?;
+                Ok(self.say(
+                    ("Restored", "Would restore"),
+                    format!(
+                        "\"{}\" from \"{}\"{}.",
+                        from.display(),
+                        kept.display(),
+                        removed
+                    ),
+                ))
+            }
+            None if !self.exists(to) => Err(format!("\"{}\" is no longer there.", to.display())),
+            None => {
+                self.move_path(to, from)?;
+                Ok(self.say(
+                    ("Moved", "Would move"),
+                    format!("\"{}\" back to \"{}\".", to.display(), from.display()),
+                ))
+            }
+        }
+    }
+
+    /**
+     * Removes a copy, the original is still in the 'from' directory.
+     */
+    fn copied(&mut self, to: &Path, bytes: Option<u64>) -> Result<String, String> {
+        if !self.unchanged(to, bytes) {
+            return Err(format!(
+                "\"{}\" has changed since it was copied and was left in place.",
+                to.display()
+            ));
+        }
+        self.remove(to)?;
+        Ok(self.say(
+            ("Removed", "Would remove"),
+            format!("the copy \"{}\".", to.display()),
+        ))
+    }
+
+    /**
+     * Puts back the old version of a file that a transfer overwrote.
+     */
+    fn versioned(&mut self, path: &Path, version: &Path) -> Result<String, String> {
+        if !self.exists(version) {
+            return Err(format!(
+                "The old version \"{}\" of \"{}\" is no longer there.",
+                version.display(),
+                path.display()
+            ));
+        }
+        if self.exists(path) {
+            return Err(format!(
+                "\"{}\" is in the way of its old version \"{}\".",
+                path.display(),
+                version.display()
+            ));
+        }
+        self.move_path(version, path)?;
+        Ok(self.say(
+            ("Restored", "Would restore"),
+            format!(
+                "\"{}\" from its old version \"{}\".",
+                path.display(),
+                version.display()
+            ),
+        ))
+    }
+
+    fn removed_dir(&mut self, path: &Path) -> Option<Result<String, String>> {
+        if self.exists(path) {
+            return None;
+        }
+        if self.dry_run {
+            self.added.insert(path.to_path_buf());
+        } else if let Err(e) = fs::create_dir_all(path) {
+            return Some(Err(format!(
+                "Unable to recreate the directory \"{}\": {}",
+                path.display(),
+                e
+            )));
+        }
+        Some(Ok(self.say(
+            ("Recreated", "Would recreate"),
+            format!("the directory \"{}\".", path.display()),
+        )))
+    }
+
+    fn say(&self, (done, would): (&str, &str), what: String) -> String {
+        format!("{} {}", if self.dry_run { would } else { done }, what)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.added.contains(path) || !self.gone.contains(path) && path.exists()
+    }
+
+    /**
+     * Whether a file still has the size it was transferred with.
+     */
+    fn unchanged(&self, path: &Path, bytes: Option<u64>) -> bool {
+        if self.added.contains(path) {
+            return false;
+        }
+        match (bytes, fs::metadata(path)) {
+            (Some(bytes), Ok(metadata)) => metadata.len() == bytes,
+            (None, Ok(_)) => true,
+            (_, Err(_)) => false,
+        }
+    }
+
+    fn remove(&mut self, path: &Path) -> Result<(), String> {
+        if self.dry_run {
+            self.added.remove(path);
+            self.gone.insert(path.to_path_buf());
+            return Ok(());
+        }
+        fs::remove_file(path).map_err(|e| format!("Unable to remove \"{}\": {}", path.display(), e))
+    }
+
+    fn move_path(&mut self, from: &Path, to: &Path) -> Result<(), String> {
+        if self.dry_run {
+            self.added.remove(from);
+            self.gone.insert(from.to_path_buf());
+            self.gone.remove(to);
+            self.added.insert(to.to_path_buf());
+            return Ok(());
+        }
+        let failed = |e: std::io::Error| {
+            format!(
+                "Unable to move \"{}\" to \"{}\": {}",
+                from.display(),
+                to.display(),
+                e
+            )
+        };
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).map_err(failed)?;
+        }
+        fsutil::move_file(from, to).map_err(failed)
+    }
+}
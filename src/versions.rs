@@ -0,0 +1,142 @@
+use super::fsutil;
+use chrono::{Local, NaiveDateTime};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/**
+ * The directory kept next to a file for its old versions, when no versions root is given.
+ */
+pub const VERSIONS_DIR: &str = ".mover-versions";
+
+const TIMESTAMP: &str = "%Y%m%d-%H%M%S";
+
+/**
+ * Where the old versions of the files in 'to_dir' are kept: a ".mover-versions" directory next to
+ * each file, or the same path relative to 'to_dir' under a separate versions root.
+ */
+pub struct Versions<'a> {
+    pub to_dir: &'a Path,
+    pub root: Option<&'a Path>,
+}
+
+/**
+ * An old version of a file, named after the file and the time it was replaced, e.g.
+ * "report.csv.20240501-142501".
+ */
+pub struct Version {
+    pub path: PathBuf,
+    pub replaced: NaiveDateTime,
+}
+
+impl Versions<'_> {
+    pub fn dir_for(&self, file: &Path) -> PathBuf {
+        let parent = file.parent().unwrap_or(Path::new(""));
+        match self.root {
+            None => parent.join(VERSIONS_DIR),
+            Some(root) => match parent.strip_prefix(self.to_dir) {
+                Ok(relative) => root.join(relative),
+                Err(_) => root.to_path_buf(),
+            },
+        }
+    }
+
+    /**
+     * Moves 'file' into its versions directory before it is overwritten.
+     */
+    pub fn keep(&self, file: &Path) -> io::Result<PathBuf> {
+        let dir = self.dir_for(file);
+        fs::create_dir_all(&dir)?;
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let time = Local::now().format(TIMESTAMP);
+        let mut path = dir.join(format!("{}.{}", name, time));
+        let mut n = 1;
+        while path.exists() {
+            path = dir.join(format!("{}.{}-{}", name, time, n));
+            n += 1;
+        }
+        fsutil::move_file(file, &path)?;
+        Ok(path)
+    }
+
+    /**
+     * The old versions of 'file', newest first.
+     */
+    pub fn list(&self, file: &Path) -> io::Result<Vec<Version>> {
+        let prefix = format!(
+            "{}.",
+            file.file_name().unwrap_or_default().to_string_lossy()
+        );
+        let mut versions: Vec<(NaiveDateTime, usize, PathBuf)> = Vec::new();
+        let entries = match fs::read_dir(self.dir_for(file)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let stamp = match name.strip_prefix(&prefix) {
+                Some(stamp) => stamp,
+                None => continue,
+            };
+            // A collision within the same second adds "-<n>" to the time.
+            let (time, n) = match (stamp.get(..15), stamp.get(15..)) {
+                (Some(time), Some(n)) => (time, n),
+                _ => continue,
+            };
+            let n = match n.strip_prefix('-') {
+                Some(n) => n.parse().ok(),
+                None if n.is_empty() => Some(0),
+                None => None,
+            };
+            match (NaiveDateTime::parse_from_str(time, TIMESTAMP), n) {
+                (Ok(replaced), Some(n)) => versions.push((replaced, n, entry.path())),
+                _ => continue,
+            }
+        }
+        versions.sort_by_key(|(replaced, n, _)| std::cmp::Reverse((*replaced, *n)));
+        Ok(versions
+            .into_iter()
+            .map(|(replaced, _, path)| Version { path, replaced })
+            .collect())
+    }
+
+    /**
+     * Removes the versions of 'file' beyond the newest 'keep'. Returns the removed paths and their
+     * sizes.
+     */
+    pub fn prune(&self, file: &Path, keep: usize) -> io::Result<Vec<(PathBuf, u64)>> {
+        let mut removed = Vec::new();
+        for version in self.list(file)?.into_iter().skip(keep) {
+            let len = fs::metadata(&version.path).map_or(0, |m| m.len());
+            fs::remove_file(&version.path)?;
+            removed.push((version.path, len));
+        }
+        Ok(removed)
+    }
+
+    /**
+     * Puts back version 'n' of 'file', 1 being the newest. The current file is kept as a version
+     * first, so a restore can itself be undone. The restored version stays in the versions
+     * directory.
+     */
+    pub fn restore(&self, file: &Path, n: usize) -> Result<PathBuf, String> {
+        let versions = self.list(file).map_err(|e| e.to_string())?;
+        let version = match n.checked_sub(1).and_then(|i| versions.get(i)) {
+            Some(version) => version.path.clone(),
+            None => {
+                return Err(format!(
+                    "\"{}\" has {} version(s), there is no version {}.",
+                    file.to_string_lossy(),
+                    versions.len(),
+                    n
+                ))
+            }
+        };
+        if file.exists() {
+            self.keep(file).map_err(|e| e.to_string())?;
+        }
+        fs::copy(&version, file).map_err(|e| e.to_string())?;
+        Ok(version)
+    }
+}
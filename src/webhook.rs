@@ -0,0 +1,430 @@
+use super::options::{Level, Sink};
+use chrono::{Local, SecondsFormat};
+use serde_json::{json, Map, Value};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/**
+ * Every event a webhook can be sent for.
+ */
+pub const EVENTS: [&str; 6] = [
+    "transfer",
+    "summary",
+    "error",
+    "path-missing",
+    "path-restored",
+    "fatal",
+];
+
+/**
+ * Settings shared by every webhook URL.
+ */
+#[derive(Clone)]
+pub struct Config {
+    pub urls: Vec<String>,
+    pub events: Vec<String>,
+    pub batch: Duration,
+    pub retries: u32,
+    pub timeout: Duration,
+}
+
+impl Config {
+    pub fn describe(&self) -> String {
+        format!(
+            "{} (events {}, batch {}s, {} retries, timeout {}s)",
+            self.urls.join(", "),
+            self.events.join(","),
+            self.batch.as_secs(),
+            self.retries,
+            self.timeout.as_secs()
+        )
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            urls: Vec::new(),
+            events: EVENTS.iter().map(|e| e.to_string()).collect(),
+            batch: Duration::from_secs(5),
+            retries: 3,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+enum Message {
+    Event(Value),
+    Flush(Sender<()>),
+}
+
+/**
+ * A handle for sending events to the webhook thread. Sending never blocks: events are queued and
+ * posted in batches from the background.
+ */
+#[derive(Clone)]
+pub struct Webhooks {
+    sender: Sender<Message>,
+    events: Vec<String>,
+    label: String,
+    timeout: Duration,
+}
+
+impl Webhooks {
+    /**
+     * Checks the URLs and starts the webhook thread.
+     */
+    pub fn start(config: Config, label: &str) -> Result<Webhooks, String> {
+        let mut urls = Vec::new();
+        for url in &config.urls {
+            urls.push(Url::parse(url)?);
+        }
+        let (sender, receiver) = mpsc::channel();
+        let webhooks = Webhooks {
+            sender,
+            events: config.events.clone(),
+            label: label.to_string(),
+            timeout: delivery_time(&config),
+        };
+        thread::spawn(move || run(receiver, urls, config));
+        Ok(webhooks)
+    }
+
+    /**
+     * Queues an event if it is enabled. 'data' is an object of extra fields.
+     */
+    pub fn notify(&self, event: &str, data: Value) {
+        if !self.events.iter().any(|e| e == event) {
+            return;
+        }
+        let mut body = Map::new();
+        body.insert(String::from("event"), json!(event));
+        body.insert(
+            String::from("time"),
+            json!(Local::now().to_rfc3339_opts(SecondsFormat::Secs, false)),
+        );
+        body.insert(String::from("label"), json!(self.label));
+        if let Value::Object(data) = data {
+            body.extend(data);
+        }
+        // The thread only stops with the program.
+        let _ = self.sender.send(Message::Event(Value::Object(body)));
+    }
+
+    /**
+     * Sends everything queued right away and waits until it has been delivered or given up on. Only
+     * used before exiting. A batch may already be on its way, so this waits for up to two batches.
+     */
+    pub fn flush(&self) {
+        let (done, wait) = mpsc::channel();
+        if self.sender.send(Message::Flush(done)).is_ok() {
+            let _ = wait.recv_timeout(self.timeout * 2 + Duration::from_secs(1));
+        }
+    }
+}
+
+/**
+ * Sends logged errors to the webhooks. Errors that carry an exit code are fatal.
+ */
+pub struct ErrorSink {
+    webhooks: Webhooks,
+}
+
+impl ErrorSink {
+    pub fn new(webhooks: Webhooks) -> ErrorSink {
+        ErrorSink { webhooks }
+    }
+}
+
+impl Sink for ErrorSink {
+    fn level(&self) -> Level {
+        Level::Error
+    }
+
+    fn name(&self) -> String {
+        String::from("webhooks")
+    }
+
+    fn send(&mut self, _level: Level, msg: &str, fields: &[(&str, String)]) -> io::Result<()> {
+        let mut data = Map::new();
+        data.insert(String::from("message"), json!(msg.trim_end()));
+        for (name, value) in fields {
+            data.insert(name.to_lowercase(), json!(value));
+        }
+        let event = if fields.iter().any(|(name, _)| *name == "EC") {
+            "fatal"
+        } else {
+            "error"
+        };
+        self.webhooks.notify(event, Value::Object(data));
+        Ok(())
+    }
+}
+
+/**
+ * The webhook thread. Waits for an event, collects everything else that arrives within the batch
+ * window and posts it as one request to every URL.
+ */
+fn run(receiver: Receiver<Message>, urls: Vec<Url>, config: Config) {
+    let mut batch = Vec::new();
+    loop {
+        let mut flushed = None;
+        match receiver.recv() {
+            Ok(Message::Event(event)) => batch.push(event),
+            Ok(Message::Flush(done)) => flushed = Some(done),
+            Err(_) => return,
+        }
+        let deadline = Instant::now() + config.batch;
+        while flushed.is_none() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match receiver.recv_timeout(remaining) {
+                Ok(Message::Event(event)) => batch.push(event),
+                Ok(Message::Flush(done)) => flushed = Some(done),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        if !batch.is_empty() {
+            let body = json!({ "source": "mover", "events": batch }).to_string();
+            for url in &urls {
+                deliver(url, &body, &config);
+            }
+            batch.clear();
+        }
+        if let Some(done) = flushed {
+            let _ = done.send(());
+        }
+    }
+}
+
+/**
+ * The longest it can take to deliver a batch to every URL: each attempt can take the timeout, and
+ * the delays between retries come on top.
+ */
+fn delivery_time(config: &Config) -> Duration {
+    let attempts = config.timeout * (config.retries + 1);
+    let backoff: Duration = (0..config.retries).map(retry_delay).sum();
+    (attempts + backoff) * config.urls.len() as u32
+}
+
+/**
+ * The delay before retry 'n', starting at 0: 1 second, doubled for every retry after it.
+ */
+fn retry_delay(n: u32) -> Duration {
+    Duration::from_secs(1 << n.min(16))
+}
+
+/**
+ * Posts a batch, retrying with a growing delay. Failures are printed to the console, since the log
+ * file belongs to the main thread.
+ */
+fn deliver(url: &Url, body: &str, config: &Config) {
+    for attempt in 0..=config.retries {
+        match post(url, body, config.timeout) {
+            Ok(()) => return,
+            Err(e) => {
+                if attempt == config.retries {
+                    print!(
+                        "{}\tUnable to send a webhook to {}. Error Message: {}\n\n",
+                        Local::now().naive_local().format("%F %I:%M:%S%P"),
+                        url.raw,
+                        e
+                    );
+                } else {
+                    thread::sleep(retry_delay(attempt));
+                }
+            }
+        }
+    }
+}
+
+fn post(url: &Url, body: &str, timeout: Duration) -> io::Result<()> {
+    let addr = (url.host.as_str(), url.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "The host was not found."))?;
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        url.path,
+        url.host,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    let mut status_line = String::new();
+    BufReader::new(&stream).read_line(&mut status_line)?;
+    match status_line.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => Err(io::Error::other(format!(
+            "Unexpected response \"{}\".",
+            status_line.trim_end()
+        ))),
+    }
+}
+
+/**
+ * Checks that a /webhook URL can be used, so a bad one is reported when the options are read.
+ */
+pub fn validate_url(raw: &str) -> Result<(), String> {
+    Url::parse(raw).map(|_| ())
+}
+
+/**
+ * A plain http:// URL.
+ */
+struct Url {
+    raw: String,
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl Url {
+    fn parse(raw: &str) -> Result<Url, String> {
+        let rest = match raw.strip_prefix("http://") {
+            Some(rest) => rest,
+            None => {
+                return Err(format!(
+                    "Only http:// webhook URLs are supported: \"{}\".",
+                    raw
+                ))
+            }
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = if let Some(ipv6) = authority.strip_prefix('[') {
+            match ipv6.split_once(']') {
+                Some((host, "")) => (host, Some(80)),
+                Some((host, port)) => (host, port.strip_prefix(':').and_then(parse_port)),
+                None => (ipv6, None),
+            }
+        } else {
+            match authority.rsplit_once(':') {
+                Some((host, port)) => (host, parse_port(port)),
+                None => (authority, Some(80)),
+            }
+        };
+        let port = match port {
+            Some(port) => port,
+            None => return Err(format!("Invalid port in the webhook URL \"{}\".", raw)),
+        };
+        if host.is_empty() {
+            return Err(format!("Missing host in the webhook URL \"{}\".", raw));
+        }
+        Ok(Url {
+            raw: raw.to_string(),
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+fn parse_port(port: &str) -> Option<u16> {
+    port.parse::<u16>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    /**
+     * Accepts 'responses.len()' requests, answers them with the given statuses in turn and sends
+     * back the bodies that were received.
+     */
+    fn listen(responses: Vec<&'static str>) -> (String, Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for status in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim_end().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                write!(&stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status).unwrap();
+                sender.send(String::from_utf8(body).unwrap()).unwrap();
+            }
+        });
+        (format!("http://{}/hook", addr), receiver)
+    }
+
+    fn config(url: String) -> Config {
+        Config {
+            urls: vec![url],
+            // Long enough that only the flush sends the batch.
+            batch: Duration::from_secs(60),
+            retries: 1,
+            timeout: Duration::from_secs(2),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn flush_delivers_queued_events() {
+        let (url, received) = listen(vec!["200 OK"]);
+        let webhooks = Webhooks::start(config(url), "test").unwrap();
+        webhooks.notify("transfer", json!({ "from": "a.txt" }));
+        webhooks.flush();
+        let body: Value = serde_json::from_str(&received.try_recv().unwrap()).unwrap();
+        assert_eq!(body["source"], "mover");
+        assert_eq!(body["events"][0]["event"], "transfer");
+        assert_eq!(body["events"][0]["label"], "test");
+        assert_eq!(body["events"][0]["from"], "a.txt");
+    }
+
+    #[test]
+    fn flush_waits_for_retries() {
+        let (url, received) = listen(vec!["500 Internal Server Error", "200 OK"]);
+        let webhooks = Webhooks::start(config(url), "test").unwrap();
+        webhooks.notify("summary", json!({}));
+        webhooks.flush();
+        // Both the failed attempt and the retry are done by the time flush returns.
+        assert!(received.try_recv().is_ok());
+        assert!(received.try_recv().is_ok());
+    }
+
+    #[test]
+    fn disabled_events_are_not_sent() {
+        let (url, received) = listen(vec!["200 OK"]);
+        let mut config = config(url);
+        config.events = vec![String::from("fatal")];
+        let webhooks = Webhooks::start(config, "test").unwrap();
+        webhooks.notify("transfer", json!({}));
+        webhooks.flush();
+        assert!(received.try_recv().is_err());
+    }
+
+    #[test]
+    fn delivery_time_covers_every_attempt() {
+        let mut config = config(String::from("http://localhost/"));
+        config.urls.push(String::from("http://localhost/other"));
+        config.retries = 3;
+        // Two URLs, each with 4 attempts of 2s and delays of 1s, 2s and 4s.
+        assert_eq!(delivery_time(&config), Duration::from_secs(2 * (8 + 7)));
+    }
+}